@@ -0,0 +1,97 @@
+use url::Url;
+
+use crate::error::APIError;
+
+use super::xml::configuration::Configuration as XMLConfiguration;
+
+/// The OBS instance configuration, as returned by `/configuration`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Configuration {
+    pub title: String,
+    pub description: String,
+    pub name: Option<String>,
+    pub download_on_demand: bool,
+    pub enforce_project_keys: bool,
+    pub anonymous: bool,
+    pub registration: Option<String>,
+    pub default_access_disabled: bool,
+    pub allow_user_to_create_home_project: bool,
+    pub disallow_group_creation: bool,
+    pub change_password: bool,
+    pub hide_private_options: bool,
+    pub gravatar: bool,
+    pub download_url: Option<Url>,
+    pub obs_url: Option<Url>,
+    pub api_url: Option<Url>,
+    pub ymp_url: Option<Url>,
+    pub bugzilla_url: Option<Url>,
+    pub http_proxy: Option<Url>,
+    pub no_proxy: Option<String>,
+    pub theme: Option<String>,
+    pub cleanup_after_days: Option<u32>,
+    pub cleanup_empty_projects: bool,
+    pub disable_publish_for_branches: bool,
+    pub admin_email: Option<String>,
+    pub unlisted_projects_filter: Option<String>,
+    pub unlisted_projects_filter_description: Option<String>,
+    pub tos_url: Option<Url>,
+    pub code_of_conduct: Option<String>,
+    pub schedulers: Vec<String>,
+}
+
+impl TryFrom<XMLConfiguration> for Configuration {
+    type Error = APIError;
+
+    fn try_from(value: XMLConfiguration) -> Result<Self, Self::Error> {
+        Ok(Self {
+            title: value.title,
+            description: value.description,
+            name: non_empty(value.name),
+            download_on_demand: is_on(&value.download_on_demand),
+            enforce_project_keys: is_on(&value.enforce_project_keys),
+            anonymous: is_on(&value.anonymous),
+            registration: non_empty(value.registration),
+            default_access_disabled: is_on(&value.default_access_disabled),
+            allow_user_to_create_home_project: is_on(&value.allow_user_to_create_home_project),
+            disallow_group_creation: is_on(&value.disallow_group_creation),
+            change_password: is_on(&value.change_password),
+            hide_private_options: is_on(&value.hide_private_options),
+            gravatar: is_on(&value.gravatar),
+            download_url: parse_url(value.download_url)?,
+            obs_url: parse_url(value.obs_url)?,
+            api_url: parse_url(value.api_url)?,
+            ymp_url: parse_url(value.ymp_url)?,
+            bugzilla_url: parse_url(value.bugzilla_url)?,
+            http_proxy: parse_url(value.http_proxy)?,
+            no_proxy: non_empty(value.no_proxy),
+            theme: non_empty(value.theme),
+            cleanup_after_days: non_empty(value.cleanup_after_days)
+                .map(|d| d.parse())
+                .transpose()
+                .map_err(|_| APIError::InvalidObject)?,
+            cleanup_empty_projects: is_on(&value.cleanup_empty_projects),
+            disable_publish_for_branches: is_on(&value.disable_publish_for_branches),
+            admin_email: non_empty(value.admin_email),
+            unlisted_projects_filter: non_empty(value.unlisted_projects_filter),
+            unlisted_projects_filter_description: non_empty(
+                value.unlisted_projects_filter_description,
+            ),
+            tos_url: parse_url(value.tos_url)?,
+            code_of_conduct: non_empty(value.code_of_conduct),
+            schedulers: value.schedulers.map(|s| s.arch).unwrap_or_default(),
+        })
+    }
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
+fn is_on(value: &Option<String>) -> bool {
+    value.as_deref().map(str::trim) == Some("on")
+}
+
+fn parse_url(value: Option<String>) -> Result<Option<Url>, url::ParseError> {
+    non_empty(value).map(|u| Url::parse(u.trim())).transpose()
+}
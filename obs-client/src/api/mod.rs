@@ -1,6 +1,9 @@
+pub mod configuration;
 pub mod package;
 pub mod project;
 pub mod request;
-mod xml;
+pub(crate) mod xml;
 
-pub use xml::obs::BuildArch;
+pub use xml::obs::{
+    BuildArch, Flag, FlagSwitch, Group, LocalRole, PackageCode, Person, RepositoryCode, SimpleFlag,
+};
@@ -1,10 +1,22 @@
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display, sync::Arc};
 
-use crate::error::APIError;
+use bytes::Bytes;
+use futures_util::Stream;
+use reqwest::{Response, StatusCode};
+use time::{Duration, OffsetDateTime};
 
+use crate::{client::OBSClient, error::APIError};
+
+pub use super::xml::package::{DevelPackage, Package as PackageMeta};
 use super::{
-    project::{Project, Repository},
-    xml::buildinfo::BuildInfo,
+    project::{BuildFilter, Project, Repository},
+    xml::{
+        buildinfo::BuildInfo,
+        directory::{Directory, FileList, FileListEntry},
+        history::BuildHistory,
+        package::Link,
+        status::Status,
+    },
     BuildArch,
 };
 
@@ -34,8 +46,7 @@ impl Package {
                 ("target_repository", target.name()),
                 ("target_project", &target.project().name()),
             ])
-            .build()
-            .unwrap();
+            .build()?;
         self.project.client.execute(req).await?;
         Ok(())
     }
@@ -56,8 +67,7 @@ impl Package {
                 &self.name,
                 "_buildinfo",
             ])
-            .build()
-            .unwrap();
+            .build()?;
         let resp = self.project.client.execute(req).await?;
         let buildinfo: BuildInfo =
             yaserde::de::from_str(&resp.text().await?).map_err(APIError::XMLParseError)?;
@@ -76,6 +86,453 @@ impl Package {
             })
             .collect())
     }
+
+    pub async fn meta(&self) -> Result<PackageMeta, APIError> {
+        let req = self
+            .project
+            .client
+            .get(&["source", &self.project.name(), &self.name, "_meta"])
+            .build()?;
+        let resp = self.project.client.execute(req).await?;
+        yaserde::de::from_str(&resp.text().await?).map_err(APIError::XMLParseError)
+    }
+
+    pub async fn set_meta(&self, meta: &PackageMeta) -> Result<(), APIError> {
+        let req = self
+            .project
+            .client
+            .put(&["source", &self.project.name(), &self.name, "_meta"])
+            .body(yaserde::ser::to_string(meta).map_err(APIError::XMLParseError)?)
+            .build()?;
+        self.project.client.execute(req).await?;
+        Ok(())
+    }
+
+    /// Create the package from `meta`, its name and project being set to this package
+    pub async fn create(&self, mut meta: PackageMeta) -> Result<(), APIError> {
+        meta.name = Some(self.name.clone());
+        meta.project = Some(self.project.name());
+        self.set_meta(&meta).await
+    }
+
+    /// Branch the package into `target_project`, by default `home:<user>:branches:<project>`,
+    /// returning the new package
+    pub async fn branch(
+        &self,
+        target_project: Option<&str>,
+        options: BranchOptions,
+    ) -> Result<Package, APIError> {
+        let mut query = vec![("cmd", "branch")];
+        if let Some(target_project) = target_project {
+            query.push(("target_project", target_project));
+        }
+        if let Some(target_package) = &options.target_package {
+            query.push(("target_package", target_package));
+        }
+        for (flag, set) in [
+            ("force", options.force),
+            ("missingok", options.missingok),
+            ("noaccess", options.noaccess),
+            ("extend_package_names", options.extend_package_names),
+        ] {
+            if set {
+                query.push((flag, "1"));
+            }
+        }
+        let req = self
+            .project
+            .client
+            .post(&["source", &self.project.name(), &self.name])
+            .query(&query)
+            .build()?;
+        let resp = self.project.client.execute(req).await?;
+        let status: Status =
+            yaserde::de::from_str(&resp.text().await?).map_err(APIError::XMLParseError)?;
+        let project = status
+            .data("targetproject")
+            .ok_or(APIError::InvalidObject)?;
+        let package = status
+            .data("targetpackage")
+            .ok_or(APIError::InvalidObject)?;
+        Ok(Package::from_name(
+            package.to_string(),
+            Project::from_name(self.project.client.clone(), project),
+        ))
+    }
+
+    /// Copy the sources into `target`, created from this package meta if it does not exist
+    pub async fn copy_to(
+        &self,
+        target: &Package,
+        options: CopyOptions,
+    ) -> Result<Package, APIError> {
+        self.create_target(target).await?;
+        let source_project = self.project.name();
+        let mut query = vec![
+            ("cmd", "copy"),
+            ("oproject", source_project.as_str()),
+            ("opackage", self.name.as_str()),
+        ];
+        for (flag, set) in [
+            ("expand", options.expand),
+            ("keeplink", options.keeplink),
+            ("withhistory", options.withhistory),
+            ("withbinaries", options.withbinaries),
+        ] {
+            if set {
+                query.push((flag, "1"));
+            }
+        }
+        if let Some(comment) = &options.comment {
+            query.push(("comment", comment));
+        }
+        let req = self
+            .project
+            .client
+            .post(&["source", &target.project.name(), &target.name])
+            .query(&query)
+            .build()?;
+        self.project.client.execute(req).await?;
+        Ok(target.clone())
+    }
+
+    /// Make `target` a link to this package, creating it from this package meta if needed
+    pub async fn link_to(&self, target: &Package) -> Result<Package, APIError> {
+        self.create_target(target).await?;
+        let link = yaserde::ser::to_string(&Link {
+            project: self.project.name(),
+            package: self.name.clone(),
+        })
+        .map_err(APIError::XMLParseError)?;
+        target
+            .put_file("_link", link.into_bytes(), Some("Link created"))
+            .await?;
+        Ok(target.clone())
+    }
+
+    /// Like osc, new targets get the meta of the source without its maintainers,
+    /// devel package and scmsync
+    async fn create_target(&self, target: &Package) -> Result<(), APIError> {
+        match target.meta().await {
+            Ok(_) => Ok(()),
+            Err(e) if e.http_status() == Some(StatusCode::NOT_FOUND) => {
+                let meta = PackageMeta {
+                    person: vec![],
+                    group: vec![],
+                    devel: None,
+                    scmsync: None,
+                    ..self.meta().await?
+                };
+                target.create(meta).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn rebuild(
+        &self,
+        repository: Option<&str>,
+        arch: Option<BuildArch>,
+    ) -> Result<(), APIError> {
+        self.build_command("rebuild", repository, arch).await
+    }
+
+    pub async fn abort_build(
+        &self,
+        repository: Option<&str>,
+        arch: Option<BuildArch>,
+    ) -> Result<(), APIError> {
+        self.build_command("abortbuild", repository, arch).await
+    }
+
+    pub async fn wipe(
+        &self,
+        repository: Option<&str>,
+        arch: Option<BuildArch>,
+    ) -> Result<(), APIError> {
+        self.build_command("wipe", repository, arch).await
+    }
+
+    pub async fn restart_build(
+        &self,
+        repository: Option<&str>,
+        arch: Option<BuildArch>,
+    ) -> Result<(), APIError> {
+        self.build_command("restartbuild", repository, arch).await
+    }
+
+    async fn build_command(
+        &self,
+        command: &str,
+        repository: Option<&str>,
+        arch: Option<BuildArch>,
+    ) -> Result<(), APIError> {
+        let filter = BuildFilter {
+            repository: repository.into_iter().map(str::to_string).collect(),
+            arch: arch.into_iter().collect(),
+            package: vec![self.name.clone()],
+            ..Default::default()
+        };
+        self.project.build_command(command, &filter).await
+    }
+
+    /// Stream the build log, chunk by chunk. With [`BuildLogOptions::follow`], the log of
+    /// a running build is followed until the build completes.
+    pub fn build_log(
+        &self,
+        repository: &str,
+        arch: BuildArch,
+        options: BuildLogOptions,
+    ) -> impl Stream<Item = Result<Bytes, APIError>> + Send + 'static {
+        let state = BuildLogState {
+            client: self.project.client.clone(),
+            route: vec![
+                "build".to_string(),
+                self.project.name(),
+                repository.to_string(),
+                arch.to_string(),
+                self.name.clone(),
+                "_log".to_string(),
+            ],
+            offset: options.start.unwrap_or_default(),
+            options,
+            response: None,
+            received: false,
+            done: false,
+        };
+        futures_util::stream::unfold(state, |mut state| async move {
+            loop {
+                if state.done {
+                    return None;
+                }
+                if let Some(response) = &mut state.response {
+                    match response.chunk().await {
+                        Ok(Some(chunk)) => {
+                            state.offset += chunk.len() as u64;
+                            state.received = true;
+                            return Some((Ok(chunk), state));
+                        }
+                        // A round without new data means the build is over
+                        Ok(None) if state.options.follow && state.received => {
+                            state.response = None;
+                        }
+                        Ok(None) => return None,
+                        Err(e) => {
+                            state.done = true;
+                            return Some((Err(e.into()), state));
+                        }
+                    }
+                }
+                if state.options.end.is_some_and(|end| state.offset >= end) {
+                    return None;
+                }
+                state.received = false;
+                match state.request().await {
+                    Ok(response) => state.response = Some(response),
+                    Err(e) => {
+                        state.done = true;
+                        return Some((Err(e), state));
+                    }
+                }
+            }
+        })
+    }
+
+    /// Builds of the package in a repository and architecture, oldest first
+    pub async fn build_history(
+        &self,
+        repository: &str,
+        arch: BuildArch,
+    ) -> Result<Vec<BuildHistoryEntry>, APIError> {
+        let req = self
+            .project
+            .client
+            .get(&[
+                "build",
+                &self.project.name(),
+                repository,
+                &arch.to_string(),
+                &self.name,
+                "_history",
+            ])
+            .build()?;
+        let resp = self.project.client.execute(req).await?;
+        let xml_resp: BuildHistory =
+            yaserde::de::from_str(&resp.text().await?).map_err(APIError::XMLParseError)?;
+        xml_resp
+            .entry
+            .into_iter()
+            .map(|e| {
+                Ok(BuildHistoryEntry {
+                    rev: e.rev,
+                    srcmd5: e.srcmd5,
+                    versrel: e.versrel,
+                    bcnt: e.bcnt,
+                    time: OffsetDateTime::from_unix_timestamp(e.time)
+                        .map_err(|_| APIError::InvalidObject)?,
+                    duration: e.duration.map(|d| Duration::seconds(d as i64)),
+                })
+            })
+            .collect()
+    }
+
+    /// List the source files at `rev`, the latest revision if `None`.
+    /// With `expand`, a link is resolved to the sources merged with its target.
+    pub async fn files(
+        &self,
+        rev: Option<&str>,
+        expand: bool,
+    ) -> Result<SourceDirectory, APIError> {
+        let mut query = vec![];
+        if let Some(rev) = rev {
+            query.push(("rev", rev));
+        }
+        if expand {
+            query.push(("expand", "1"));
+        }
+        let req = self
+            .project
+            .client
+            .get(&["source", &self.project.name(), &self.name])
+            .query(&query)
+            .build()?;
+        let resp = self.project.client.execute(req).await?;
+        let directory: Directory =
+            yaserde::de::from_str(&resp.text().await?).map_err(APIError::XMLParseError)?;
+        SourceDirectory::from_directory(directory, &self.project)
+    }
+
+    pub async fn get_file(&self, name: &str, rev: Option<&str>) -> Result<Vec<u8>, APIError> {
+        let req = self
+            .project
+            .client
+            .get(&["source", &self.project.name(), &self.name, name]);
+        let req = match rev {
+            Some(rev) => req.query(&[("rev", rev)]),
+            None => req,
+        }
+        .build()?;
+        let resp = self.project.client.execute(req).await?;
+        Ok(resp.bytes().await?.to_vec())
+    }
+
+    /// Upload a file, creating a new revision
+    pub async fn put_file(
+        &self,
+        name: &str,
+        content: Vec<u8>,
+        comment: Option<&str>,
+    ) -> Result<(), APIError> {
+        let req = self
+            .project
+            .client
+            .put(&["source", &self.project.name(), &self.name, name]);
+        let req = match comment {
+            Some(comment) => req.query(&[("comment", comment)]),
+            None => req,
+        }
+        .body(content)
+        .build()?;
+        self.project.client.execute(req).await?;
+        Ok(())
+    }
+
+    /// Apply all the changes as a single new revision, following osc's `commitfilelist`
+    /// flow: send the new file list, upload the contents OBS reports as missing, then
+    /// send the file list again.
+    pub async fn commit(
+        &self,
+        changes: Vec<FileChange>,
+        message: &str,
+    ) -> Result<SourceDirectory, APIError> {
+        let mut files: BTreeMap<String, String> = self
+            .files(None, false)
+            .await?
+            .files
+            .into_iter()
+            .map(|f| (f.name, f.md5))
+            .collect();
+        let mut contents = BTreeMap::new();
+        for change in changes {
+            match change {
+                FileChange::Put { name, content } => {
+                    let md5 = openssl::hash::hash(openssl::hash::MessageDigest::md5(), &content)
+                        .map_err(|_| APIError::InvalidObject)?;
+                    files.insert(name.clone(), hex(&md5));
+                    contents.insert(name, content);
+                }
+                FileChange::Delete { name } => {
+                    files.remove(&name);
+                    contents.remove(&name);
+                }
+            }
+        }
+        let filelist = yaserde::ser::to_string(&FileList {
+            entry: files
+                .into_iter()
+                .map(|(name, md5)| FileListEntry { name, md5 })
+                .collect(),
+        })
+        .map_err(APIError::XMLParseError)?;
+
+        let directory = self.commit_filelist(&filelist, message).await?;
+        let directory = match directory.error.as_deref() {
+            Some("missing") => {
+                let mut uploads = Vec::new();
+                for entry in &directory.entry {
+                    match contents.remove(&entry.name) {
+                        Some(content) => uploads.push((entry.name.clone(), content)),
+                        // Only the contents of the changed files can be uploaded
+                        None => return Err(commit_error(directory)),
+                    }
+                }
+                for (name, content) in uploads {
+                    // Upload the content without creating a revision
+                    let req = self
+                        .project
+                        .client
+                        .put(&["source", &self.project.name(), &self.name, &name])
+                        .query(&[("rev", "repository")])
+                        .body(content)
+                        .build()?;
+                    self.project.client.execute(req).await?;
+                }
+                self.commit_filelist(&filelist, message).await?
+            }
+            _ => directory,
+        };
+        match directory.error {
+            None => SourceDirectory::from_directory(directory, &self.project),
+            Some(_) => Err(commit_error(directory)),
+        }
+    }
+
+    async fn commit_filelist(&self, filelist: &str, message: &str) -> Result<Directory, APIError> {
+        let req = self
+            .project
+            .client
+            .post(&["source", &self.project.name(), &self.name])
+            .query(&[("cmd", "commitfilelist"), ("comment", message)])
+            .body(filelist.to_string())
+            .build()?;
+        let resp = self.project.client.execute(req).await?;
+        yaserde::de::from_str(&resp.text().await?).map_err(APIError::XMLParseError)
+    }
+
+    /// Remove a file, creating a new revision
+    pub async fn delete_file(&self, name: &str, comment: Option<&str>) -> Result<(), APIError> {
+        let req = self
+            .project
+            .client
+            .delete(&["source", &self.project.name(), &self.name, name]);
+        let req = match comment {
+            Some(comment) => req.query(&[("comment", comment)]),
+            None => req,
+        }
+        .build()?;
+        self.project.client.execute(req).await?;
+        Ok(())
+    }
 }
 
 impl Display for Package {
@@ -92,3 +549,480 @@ pub struct BinPackage {
     pub arch: BuildArch,
     pub repository: Repository,
 }
+
+#[derive(Debug, Clone, Default)]
+pub struct BranchOptions {
+    pub target_package: Option<String>,
+    /// Branch even if the target already exists
+    pub force: bool,
+    /// Allow branching a package which does not exist yet
+    pub missingok: bool,
+    /// Create the branch hidden from other users
+    pub noaccess: bool,
+    /// Suffix the target package name with the source project name
+    pub extend_package_names: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CopyOptions {
+    /// Copy the sources with links expanded
+    pub expand: bool,
+    /// Keep the link when copying a linked package
+    pub keeplink: bool,
+    /// Copy the full revision history
+    pub withhistory: bool,
+    /// Copy the built binaries as well
+    pub withbinaries: bool,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BuildLogOptions {
+    /// Byte offset to start reading from
+    pub start: Option<u64>,
+    /// Byte offset to stop reading at
+    pub end: Option<u64>,
+    /// Keep reading while the package is building instead of returning what is available
+    pub follow: bool,
+    /// Read the log of the last finished build instead of the current one
+    pub last: bool,
+}
+
+struct BuildLogState {
+    client: Arc<OBSClient>,
+    route: Vec<String>,
+    options: BuildLogOptions,
+    offset: u64,
+    response: Option<Response>,
+    received: bool,
+    done: bool,
+}
+
+impl BuildLogState {
+    async fn request(&self) -> Result<Response, APIError> {
+        let route: Vec<&str> = self.route.iter().map(String::as_str).collect();
+        let mut query = vec![("start", self.offset.to_string())];
+        if let Some(end) = self.options.end {
+            query.push(("end", end.to_string()));
+        }
+        if self.options.last {
+            query.push(("last", "1".to_string()));
+        }
+        // Without nostream, OBS keeps the connection open while the build is running
+        if !self.options.follow {
+            query.push(("nostream", "1".to_string()));
+        }
+        let req = self.client.get(&route).query(&query).build()?;
+        self.client.execute(req).await
+    }
+}
+
+/// A change to the sources, applied by [`Package::commit`]
+#[derive(Debug, Clone)]
+pub enum FileChange {
+    /// Add a file or replace its content
+    Put {
+        name: String,
+        content: Vec<u8>,
+    },
+    Delete {
+        name: String,
+    },
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `commitfilelist` refuses a commit with a `200 OK` listing the files concerned,
+/// its `error` attribute giving the reason
+fn commit_error(directory: Directory) -> APIError {
+    let code = directory.error.unwrap_or_default();
+    let files = directory
+        .entry
+        .iter()
+        .map(|e| e.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    APIError::OBSStatus {
+        http_status: StatusCode::OK,
+        summary: Some(format!("{} files: {}", code, files)),
+        code,
+        details: None,
+    }
+}
+
+/// Source files of a package at a given revision
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceDirectory {
+    pub rev: Option<String>,
+    pub vrev: Option<String>,
+    pub srcmd5: Option<String>,
+    pub files: Vec<SourceFile>,
+    pub linkinfo: Option<LinkInfo>,
+}
+
+impl SourceDirectory {
+    fn from_directory(directory: Directory, project: &Project) -> Result<Self, APIError> {
+        let files = directory
+            .entry
+            .into_iter()
+            .map(|e| {
+                Ok(SourceFile {
+                    name: e.name,
+                    md5: e.md5.unwrap_or_default(),
+                    size: e.size.unwrap_or_default(),
+                    mtime: OffsetDateTime::from_unix_timestamp(e.mtime.unwrap_or_default())
+                        .map_err(|_| APIError::InvalidObject)?,
+                })
+            })
+            .collect::<Result<_, APIError>>()?;
+        Ok(Self {
+            rev: directory.rev,
+            vrev: directory.vrev,
+            srcmd5: directory.srcmd5,
+            files,
+            linkinfo: directory.linkinfo.map(|l| LinkInfo {
+                target: Package::from_name(
+                    l.package,
+                    Project::from_name(project.client.clone(), &l.project),
+                ),
+                srcmd5: l.srcmd5,
+                baserev: l.baserev,
+                xsrcmd5: l.xsrcmd5,
+                lsrcmd5: l.lsrcmd5,
+                error: l.error,
+            }),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    pub name: String,
+    pub md5: String,
+    pub size: u64,
+    pub mtime: OffsetDateTime,
+}
+
+/// Set when the package is a link to another package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkInfo {
+    pub target: Package,
+    pub srcmd5: Option<String>,
+    pub baserev: Option<String>,
+    /// Source md5 of the expanded sources
+    pub xsrcmd5: Option<String>,
+    pub lsrcmd5: Option<String>,
+    /// Set when the link can not be expanded, e.g. on merge conflicts
+    pub error: Option<String>,
+}
+
+/// A successful build of the package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildHistoryEntry {
+    pub rev: String,
+    pub srcmd5: String,
+    /// Version and release of the built binaries, e.g. `1.2.3-4.1`
+    pub versrel: String,
+    /// Build counter, incremented on each rebuild of the same sources
+    pub bcnt: u32,
+    pub time: OffsetDateTime,
+    pub duration: Option<Duration>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::api::xml::obs::LocalRole;
+    use crate::test_utils::{api_fixture, xml_events, MockResponse, MockServer};
+
+    const LISTING: &str = r#"<directory name="pkg" rev="1" srcmd5="1111">
+  <entry name="gone" md5="4567" size="1" mtime="1"/>
+  <entry name="old" md5="0123" size="3" mtime="1"/>
+</directory>"#;
+    const COMMITTED: &str = r#"<directory name="pkg" rev="2" srcmd5="2222">
+  <entry name="new" md5="9a0364b9e99bb480dd25e1f0284c8555" size="7" mtime="2"/>
+  <entry name="old" md5="0123" size="3" mtime="1"/>
+</directory>"#;
+
+    fn missing(name: &str) -> String {
+        format!(
+            r#"<directory name="pkg" error="missing"><entry name="{}" md5="9a0364b9e99bb480dd25e1f0284c8555" hash="sha256:0"/></directory>"#,
+            name
+        )
+    }
+
+    /// Serve the package listing, then answer each `commitfilelist` in turn
+    async fn serve(commits: Vec<String>) -> MockServer {
+        let posts = AtomicUsize::new(0);
+        MockServer::start(move |request| match request.method.as_str() {
+            "GET" => MockResponse::new(200).body(LISTING),
+            "PUT" => MockResponse::new(200).body("<status code=\"ok\"/>"),
+            _ => MockResponse::new(200).body(commits[posts.fetch_add(1, Ordering::SeqCst)].clone()),
+        })
+        .await
+    }
+
+    fn package(server: &MockServer) -> Package {
+        Package::from_name(
+            "pkg".to_string(),
+            Project::from_name(Arc::new(server.client()), "prj"),
+        )
+    }
+
+    fn changes() -> Vec<FileChange> {
+        vec![
+            FileChange::Put {
+                name: "new".to_string(),
+                content: b"content".to_vec(),
+            },
+            FileChange::Delete {
+                name: "gone".to_string(),
+            },
+        ]
+    }
+
+    #[tokio::test]
+    async fn uploads_missing_files_then_commits() {
+        let server = serve(vec![missing("new"), COMMITTED.to_string()]).await;
+        let directory = package(&server).commit(changes(), "msg").await.unwrap();
+        assert_eq!(directory.rev.as_deref(), Some("2"));
+        assert_eq!(directory.files.len(), 2);
+
+        let requests = server.requests();
+        let calls: Vec<(&str, &str)> = requests
+            .iter()
+            .map(|r| (r.method.as_str(), r.path.as_str()))
+            .collect();
+        assert_eq!(
+            calls,
+            [
+                ("GET", "/source/prj/pkg"),
+                ("POST", "/source/prj/pkg?cmd=commitfilelist&comment=msg"),
+                ("PUT", "/source/prj/pkg/new?rev=repository"),
+                ("POST", "/source/prj/pkg?cmd=commitfilelist&comment=msg"),
+            ]
+        );
+        assert_eq!(requests[2].body, b"content");
+        let filelist = String::from_utf8(requests[1].body.clone()).unwrap();
+        assert!(filelist.contains(r#"<entry name="new" md5="9a0364b9e99bb480dd25e1f0284c8555" />"#));
+        assert!(filelist.contains(r#"<entry name="old" md5="0123" />"#));
+        assert!(!filelist.contains("gone"));
+        assert_eq!(requests[1].body, requests[3].body);
+    }
+
+    #[tokio::test]
+    async fn still_missing_after_upload_is_an_error() {
+        let server = serve(vec![missing("new"), missing("new")]).await;
+        match package(&server).commit(changes(), "msg").await {
+            Err(APIError::OBSStatus { code, summary, .. }) => {
+                assert_eq!(code, "missing");
+                assert_eq!(summary.as_deref(), Some("missing files: new"));
+            }
+            other => panic!("commit should fail, got {:?}", other),
+        }
+        assert_eq!(server.requests().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn missing_unchanged_file_is_an_error() {
+        let server = serve(vec![missing("old")]).await;
+        let error = package(&server).commit(changes(), "msg").await.unwrap_err();
+        assert_eq!(error.obs_code(), Some("missing"));
+        // Nothing we could upload
+        assert!(server.requests().iter().all(|r| r.method != "PUT"));
+    }
+
+    const LOG: &str = "/build/prj/repo/x86_64/pkg/_log";
+
+    async fn read_log(server: &MockServer, options: BuildLogOptions) -> Vec<String> {
+        package(server)
+            .build_log("repo", BuildArch::X86_64, options)
+            .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+            .collect()
+            .await
+    }
+
+    fn paths(server: &MockServer) -> Vec<String> {
+        server.requests().into_iter().map(|r| r.path).collect()
+    }
+
+    #[tokio::test]
+    async fn reads_log_range_once() {
+        let server =
+            MockServer::start(|_| MockResponse::new(200).chunks(&["[ 1s] ", "done"])).await;
+        let options = BuildLogOptions {
+            start: Some(10),
+            end: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(read_log(&server, options).await, ["[ 1s] ", "done"]);
+        assert_eq!(
+            paths(&server),
+            [format!("{}?start=10&end=100&nostream=1", LOG)]
+        );
+    }
+
+    #[tokio::test]
+    async fn follows_log_until_no_new_data() {
+        let server = MockServer::start(|request| match request.path.split_once("?start=") {
+            Some((_, "0")) => MockResponse::new(200).chunks(&["abc", "def"]),
+            Some((_, "6")) => MockResponse::new(200).chunks(&["gh"]),
+            _ => MockResponse::new(200).body(""),
+        })
+        .await;
+        let options = BuildLogOptions {
+            follow: true,
+            ..Default::default()
+        };
+        assert_eq!(read_log(&server, options).await, ["abc", "def", "gh"]);
+        assert_eq!(
+            paths(&server),
+            [
+                format!("{}?start=0", LOG),
+                format!("{}?start=6", LOG),
+                format!("{}?start=8", LOG),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn follow_stops_at_end() {
+        let server = MockServer::start(|_| MockResponse::new(200).chunks(&["abc", "def"])).await;
+        let options = BuildLogOptions {
+            start: Some(2),
+            end: Some(8),
+            follow: true,
+            ..Default::default()
+        };
+        assert_eq!(read_log(&server, options).await, ["abc", "def"]);
+        assert_eq!(paths(&server), [format!("{}?start=2&end=8", LOG)]);
+    }
+
+    #[tokio::test]
+    async fn files_reads_listing_and_linkinfo() {
+        let server = MockServer::start(|_| {
+            MockResponse::new(200).body(api_fixture("source_directory_link.xml"))
+        })
+        .await;
+        let directory = package(&server).files(Some("3"), true).await.unwrap();
+        assert_eq!(server.requests()[0].path, "/source/prj/pkg?rev=3&expand=1");
+
+        assert_eq!(directory.rev.as_deref(), Some("3"));
+        assert_eq!(directory.vrev.as_deref(), Some("5"));
+        assert_eq!(
+            directory.srcmd5.as_deref(),
+            Some("4b1c4a5e1e5d8d3f1f2ae3f6b6c6d0b2")
+        );
+        let names: Vec<&str> = directory.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["_link", "pkg-1.2.tar.xz", "pkg.spec"]);
+        let spec = &directory.files[2];
+        assert_eq!(spec.md5, "e1d5b8b0b2c1d4e7a9f8c3b2a1d0e9f8");
+        assert_eq!(spec.size, 2048);
+        assert_eq!(spec.mtime.unix_timestamp(), 1700000100);
+
+        let linkinfo = directory.linkinfo.unwrap();
+        assert_eq!(linkinfo.target.to_string(), "openSUSE:Factory/pkg");
+        assert_eq!(
+            linkinfo.xsrcmd5.as_deref(),
+            Some("8d5e957f297893487bd98fa830fa6413")
+        );
+        assert_eq!(linkinfo.error, None);
+    }
+
+    #[tokio::test]
+    async fn meta_round_trips_and_create_renames() {
+        let server = MockServer::start(|request| match request.method.as_str() {
+            "GET" => MockResponse::new(200).body(api_fixture("package_meta.xml")),
+            _ => MockResponse::new(200).body("<status code=\"ok\"/>"),
+        })
+        .await;
+        let meta = package(&server).meta().await.unwrap();
+        assert_eq!(meta.title, "Example tool");
+        let devel = meta.devel.as_ref().unwrap();
+        assert_eq!(devel.project.as_deref(), Some("devel:languages"));
+        assert_eq!(meta.person.len(), 2);
+        assert!(matches!(meta.group[0].role, LocalRole::Reviewer));
+        let build = meta.build.as_ref().unwrap();
+        assert_eq!(
+            build.disable[0].repository.as_deref(),
+            Some("openSUSE_Leap_15.5")
+        );
+        assert_eq!(meta.bcnt_sync_tag.as_deref(), Some("pkg"));
+
+        package(&server).set_meta(&meta).await.unwrap();
+        let copy = Package::from_name(
+            "copy".to_string(),
+            Project::from_name(Arc::new(server.client()), "other"),
+        );
+        copy.create(meta).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[1].method, "PUT");
+        assert_eq!(requests[1].path, "/source/prj/pkg/_meta");
+        let sent = String::from_utf8(requests[1].body.clone()).unwrap();
+        assert_eq!(
+            xml_events(&sent),
+            xml_events(&api_fixture("package_meta.xml"))
+        );
+        assert_eq!(requests[2].path, "/source/other/copy/_meta");
+        let created: PackageMeta =
+            yaserde::de::from_str(std::str::from_utf8(&requests[2].body).unwrap()).unwrap();
+        assert_eq!(created.name.as_deref(), Some("copy"));
+        assert_eq!(created.project.as_deref(), Some("other"));
+        assert_eq!(
+            created.scmsync.as_deref(),
+            Some("https://src.example.org/pkg#main")
+        );
+    }
+
+    #[tokio::test]
+    async fn branch_returns_target_from_status() {
+        let server =
+            MockServer::start(|_| MockResponse::new(200).body(api_fixture("status_branch.xml")))
+                .await;
+        let options = BranchOptions {
+            force: true,
+            extend_package_names: true,
+            ..Default::default()
+        };
+        let branch = package(&server).branch(None, options).await.unwrap();
+        assert_eq!(branch.to_string(), "home:someone:branches:prj/pkg.prj");
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(
+            requests[0].path,
+            "/source/prj/pkg?cmd=branch&force=1&extend_package_names=1"
+        );
+    }
+
+    #[tokio::test]
+    async fn build_history_reads_durations() {
+        let server =
+            MockServer::start(|_| MockResponse::new(200).body(api_fixture("build_history.xml")))
+                .await;
+        let history = package(&server)
+            .build_history("openSUSE_Tumbleweed", BuildArch::X86_64)
+            .await
+            .unwrap();
+        assert_eq!(
+            server.requests()[0].path,
+            "/build/prj/openSUSE_Tumbleweed/x86_64/pkg/_history"
+        );
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].versrel, "1.0-1");
+        assert_eq!(history[0].bcnt, 1);
+        assert_eq!(history[0].time.unix_timestamp(), 1700000000);
+        assert_eq!(history[0].duration, None);
+        assert_eq!(history[1].srcmd5, "4f0a4e6b4ac3f1f8e1b1d4c0d8e4a9b2");
+        assert_eq!(history[1].bcnt, 2);
+        assert_eq!(history[1].duration, Some(Duration::seconds(342)));
+    }
+}
@@ -2,19 +2,26 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use itertools::Itertools;
-use time::OffsetDateTime;
+use time::{Duration, OffsetDateTime};
 
 use crate::client::OBSClient;
 use crate::error::APIError;
 
 use super::package::Package;
 use super::xml::buildepinfo::BuildDepInfo;
-use super::xml::buildresult::{ResultList, Summary};
-use super::xml::obs::{BuildArch, PackageCode, RepositoryCode};
+use super::xml::buildresult::{RepositoryResult, ResultList};
+use super::xml::history::{JobHistory, JobHistoryList};
+use super::xml::obs::{BuildArch, Flag, Group, LocalRole, PackageCode, Person, RepositoryCode};
 
 use super::xml::project::SourceInfoList;
-pub use super::xml::project::{Project as ProjectMeta, ProjectKind};
-pub use super::xml::repository::ReleaseTrigger;
+pub use super::xml::project::{
+    DevelProject, Maintains, Maintenance, Project as ProjectMeta, ProjectKind, ProjectLink,
+    VrevMode,
+};
+pub use super::xml::repository::{
+    BlockMode, Download, LinkedBuildMode, Master, Path as RepositoryPath, RebuildMode,
+    ReleaseTarget, ReleaseTrigger, RepoType, Repository as RepositoryMeta,
+};
 
 #[derive(Debug, thiserror::Error)]
 #[error("Wrong ResultList kind provided")]
@@ -56,13 +63,7 @@ impl Binary {
                 &self.name,
             ])
             .build()?;
-        let resp = self
-            .repository
-            .project
-            .client
-            .execute(req)
-            .await?
-            .error_for_status()?;
+        let resp = self.repository.project.client.execute(req).await?;
         Ok(resp.bytes().await?.to_vec())
     }
 }
@@ -177,6 +178,99 @@ impl BinaryList {
     }
 }
 
+/// Selects the builds affected by a build command, an empty field matching everything
+#[derive(Debug, Clone, Default)]
+pub struct BuildFilter {
+    pub repository: Vec<String>,
+    pub arch: Vec<BuildArch>,
+    pub package: Vec<String>,
+    /// Only builds in one of these states, e.g. [`PackageCode::Failed`]
+    pub code: Vec<PackageCode>,
+}
+
+impl BuildFilter {
+    pub(crate) fn query(&self) -> Vec<(&'static str, String)> {
+        self.repository
+            .iter()
+            .map(|r| ("repository", r.clone()))
+            .chain(self.arch.iter().map(|a| ("arch", a.to_string())))
+            .chain(self.package.iter().map(|p| ("package", p.clone())))
+            .chain(self.code.iter().map(|c| ("code", c.to_string())))
+            .collect()
+    }
+}
+
+/// Selects the jobs of a repository and architecture returned by [`Project::job_history`]
+#[derive(Debug, Clone)]
+pub struct JobHistoryFilter {
+    pub repository: String,
+    pub arch: BuildArch,
+    pub package: Vec<String>,
+    pub code: Vec<PackageCode>,
+    /// Only return the last jobs, still oldest first
+    pub limit: Option<u32>,
+}
+
+impl JobHistoryFilter {
+    pub fn new(repository: &str, arch: BuildArch) -> Self {
+        Self {
+            repository: repository.to_string(),
+            arch,
+            package: Default::default(),
+            code: Default::default(),
+            limit: None,
+        }
+    }
+
+    fn query(&self) -> Vec<(&'static str, String)> {
+        self.package
+            .iter()
+            .map(|p| ("package", p.clone()))
+            .chain(self.code.iter().map(|c| ("code", c.to_string())))
+            .chain(self.limit.iter().map(|l| ("limit", l.to_string())))
+            .collect()
+    }
+}
+
+/// A build job run by the scheduler
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobHistoryEntry {
+    pub package: Package,
+    pub rev: String,
+    pub srcmd5: String,
+    pub versrel: String,
+    pub bcnt: u32,
+    /// End of the job, when OBS reports it
+    pub time: Option<OffsetDateTime>,
+    /// Set when OBS reports both the start and the end of the job
+    pub duration: Option<Duration>,
+    pub worker: Option<String>,
+    pub reason: Option<String>,
+    pub code: PackageCode,
+}
+
+impl JobHistoryEntry {
+    fn from_job(job: JobHistory, project: &Project) -> Result<Self, APIError> {
+        let time =
+            |t: i64| OffsetDateTime::from_unix_timestamp(t).map_err(|_| APIError::InvalidObject);
+        Ok(Self {
+            package: Package::from_name(job.package, project.clone()),
+            rev: job.rev,
+            srcmd5: job.srcmd5,
+            versrel: job.versrel,
+            bcnt: job.bcnt,
+            time: job.endtime.map(time).transpose()?,
+            duration: job
+                .starttime
+                .zip(job.endtime)
+                .map(|(start, end)| Duration::seconds(end - start)),
+            worker: job.workerid,
+            reason: job.reason,
+            code: job.code,
+        })
+    }
+}
+
 pub struct ProjectSummary {
     repositories: HashMap<(String, BuildArch), RepositorySummary>,
 }
@@ -199,6 +293,14 @@ impl ProjectSummary {
     ) -> std::collections::hash_map::Iter<'_, (String, BuildArch), RepositorySummary> {
         self.repositories.iter()
     }
+
+    /// Failed, unresolvable or broken packages with their repository and architecture.
+    /// Only filled by [`Project::build_results`].
+    pub fn failed_packages(&self) -> impl Iterator<Item = (&str, &BuildArch, &PackageResult)> {
+        self.repositories.iter().flat_map(|((repo, arch), s)| {
+            s.failed_packages().map(move |p| (repo.as_str(), arch, p))
+        })
+    }
 }
 
 impl IntoIterator for ProjectSummary {
@@ -212,7 +314,10 @@ impl IntoIterator for ProjectSummary {
 
 pub struct RepositorySummary {
     code: RepositoryCode,
+    state: Option<RepositoryCode>,
+    dirty: bool,
     counts: HashMap<PackageCode, u32>,
+    packages: Vec<PackageResult>,
 }
 
 impl RepositorySummary {
@@ -226,32 +331,95 @@ impl RepositorySummary {
             .all(|(code, count)| *count == 0 || code.is_ok())
     }
 
-    fn from_summary(code: RepositoryCode, summary: Summary) -> Self {
-        Self {
-            code,
-            counts: summary
+    pub fn code(&self) -> &RepositoryCode {
+        &self.code
+    }
+
+    /// State reported by the scheduler, which can differ from [`Self::code`] while the
+    /// repository is dirty
+    pub fn state(&self) -> Option<&RepositoryCode> {
+        self.state.as_ref()
+    }
+
+    /// The scheduler has not computed the state of the repository yet
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Status of every package, empty when built from [`Project::summary`]
+    pub fn packages(&self) -> &[PackageResult] {
+        &self.packages
+    }
+
+    pub fn failed_packages(&self) -> impl Iterator<Item = &PackageResult> {
+        self.packages.iter().filter(|p| p.code.is_failed())
+    }
+
+    fn from_result(result: RepositoryResult) -> Self {
+        let packages: Vec<PackageResult> = result
+            .status
+            .into_iter()
+            .map(|s| PackageResult {
+                package: s.package,
+                code: s.code,
+                details: s.details.into_iter().next().filter(|d| !d.is_empty()),
+            })
+            .collect();
+        let counts = match result.summary {
+            Some(summary) => summary
                 .statuscount
                 .into_iter()
                 .map(|s| (s.code, s.count))
                 .collect(),
+            None => packages.iter().fold(HashMap::new(), |mut counts, p| {
+                *counts.entry(p.code.clone()).or_insert(0) += 1;
+                counts
+            }),
+        };
+        Self {
+            code: result.code,
+            state: result.state,
+            dirty: result.dirty.unwrap_or_default(),
+            counts,
+            packages,
         }
     }
 }
 
-impl TryFrom<ResultList> for ProjectSummary {
-    type Error = ResultError;
-    fn try_from(value: ResultList) -> Result<Self, Self::Error> {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageResult {
+    /// Package name, followed by `:<flavor>` for multibuild flavors
+    pub package: String,
+    pub code: PackageCode,
+    pub details: Option<String>,
+}
+
+impl PackageResult {
+    /// Package name without the multibuild flavor
+    pub fn name(&self) -> &str {
+        self.package
+            .split_once(':')
+            .map_or(self.package.as_str(), |(name, _)| name)
+    }
+
+    pub fn flavor(&self) -> Option<&str> {
+        self.package.split_once(':').map(|(_, flavor)| flavor)
+    }
+}
+
+impl From<ResultList> for ProjectSummary {
+    fn from(value: ResultList) -> Self {
         let repositories = value
             .result
             .into_iter()
             .map(|r| {
-                Ok::<_, Self::Error>((
-                    (r.repository, r.arch),
-                    RepositorySummary::from_summary(r.code, r.summary.ok_or(ResultError {})?),
-                ))
+                (
+                    (r.repository.clone(), r.arch.clone()),
+                    RepositorySummary::from_result(r),
+                )
             })
-            .collect::<Result<_, _>>()?;
-        Ok(Self { repositories })
+            .collect();
+        Self { repositories }
     }
 }
 
@@ -304,7 +472,47 @@ impl Project {
         let resp = self.client.execute(req).await?;
         let xlm_resp: ResultList =
             yaserde::de::from_str(&resp.text().await?).map_err(APIError::XMLParseError)?;
-        Ok(xlm_resp.try_into().unwrap())
+        Ok(xlm_resp.into())
+    }
+
+    /// Build status of every package matching the filter, multibuild flavors included
+    pub async fn build_results(&self, filter: &BuildFilter) -> Result<ProjectSummary, APIError> {
+        let req = self
+            .client
+            .get(&["build", &self.name, "_result"])
+            .query(&[("view", "status"), ("multibuild", "1"), ("locallink", "1")])
+            .query(&filter.query())
+            .build()?;
+        let resp = self.client.execute(req).await?;
+        let xlm_resp: ResultList =
+            yaserde::de::from_str(&resp.text().await?).map_err(APIError::XMLParseError)?;
+        Ok(xlm_resp.into())
+    }
+
+    /// Jobs of a repository and architecture, oldest first
+    pub async fn job_history(
+        &self,
+        filter: &JobHistoryFilter,
+    ) -> Result<Vec<JobHistoryEntry>, APIError> {
+        let req = self
+            .client
+            .get(&[
+                "build",
+                &self.name,
+                &filter.repository,
+                &filter.arch.to_string(),
+                "_jobhistory",
+            ])
+            .query(&filter.query())
+            .build()?;
+        let resp = self.client.execute(req).await?;
+        let xml_resp: JobHistoryList =
+            yaserde::de::from_str(&resp.text().await?).map_err(APIError::XMLParseError)?;
+        xml_resp
+            .jobhist
+            .into_iter()
+            .map(|j| JobHistoryEntry::from_job(j, self))
+            .collect()
     }
 
     pub async fn binarylist(&self) -> Result<BinaryList, APIError> {
@@ -347,9 +555,309 @@ impl Project {
             .collect())
     }
 
+    /// Trigger a rebuild of the selected packages
+    pub async fn rebuild(&self, filter: &BuildFilter) -> Result<(), APIError> {
+        self.build_command("rebuild", filter).await
+    }
+
+    /// Stop the running builds
+    pub async fn abort_build(&self, filter: &BuildFilter) -> Result<(), APIError> {
+        self.build_command("abortbuild", filter).await
+    }
+
+    /// Remove the built binaries
+    pub async fn wipe(&self, filter: &BuildFilter) -> Result<(), APIError> {
+        self.build_command("wipe", filter).await
+    }
+
+    /// Restart the builds, keeping the current scheduling
+    pub async fn restart_build(&self, filter: &BuildFilter) -> Result<(), APIError> {
+        self.build_command("restartbuild", filter).await
+    }
+
+    pub(crate) async fn build_command(
+        &self,
+        command: &str,
+        filter: &BuildFilter,
+    ) -> Result<(), APIError> {
+        let req = self
+            .client
+            .post(&["build", &self.name])
+            .query(&[("cmd", command)])
+            .query(&filter.query())
+            .build()?;
+        self.client.execute(req).await?;
+        Ok(())
+    }
+
     pub async fn meta(&self) -> Result<ProjectMeta, APIError> {
         let req = self.client.get(&["source", &self.name, "_meta"]).build()?;
         let resp = self.client.execute(req).await?;
         yaserde::de::from_str(&resp.text().await?).map_err(APIError::XMLParseError)
     }
+
+    pub async fn set_meta(&self, meta: &ProjectMeta) -> Result<(), APIError> {
+        let req = self
+            .client
+            .put(&["source", &self.name, "_meta"])
+            .body(yaserde::ser::to_string(meta).map_err(APIError::XMLParseError)?)
+            .build()?;
+        self.client.execute(req).await?;
+        Ok(())
+    }
+
+    /// Create the project from `meta`, its name being set to this project
+    pub async fn create(&self, mut meta: ProjectMeta) -> Result<(), APIError> {
+        meta.name = Some(self.name.clone());
+        self.set_meta(&meta).await
+    }
+
+    /// Delete the project, with `force` even if other projects still depend on it
+    pub async fn delete(&self, force: bool) -> Result<(), APIError> {
+        let req = self.client.delete(&["source", &self.name]);
+        let req = match force {
+            true => req.query(&[("force", "1")]),
+            false => req,
+        }
+        .build()?;
+        self.client.execute(req).await?;
+        Ok(())
+    }
+}
+
+impl ProjectMeta {
+    pub fn builder(name: &str) -> ProjectMetaBuilder {
+        ProjectMetaBuilder {
+            meta: ProjectMeta {
+                name: Some(name.to_string()),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProjectMetaBuilder {
+    meta: ProjectMeta,
+}
+
+impl ProjectMetaBuilder {
+    pub fn title(mut self, title: &str) -> Self {
+        self.meta.title = title.to_string();
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.meta.description = description.to_string();
+        self
+    }
+
+    pub fn kind(mut self, kind: ProjectKind) -> Self {
+        self.meta.kind = Some(kind);
+        self
+    }
+
+    /// Inherit the packages of `project`
+    pub fn link(mut self, project: &str) -> Self {
+        self.meta.link.push(ProjectLink {
+            project: project.to_string(),
+            vrevmode: None,
+        });
+        self
+    }
+
+    pub fn person(mut self, userid: &str, role: LocalRole) -> Self {
+        self.meta.person.push(Person {
+            userid: userid.to_string(),
+            role,
+        });
+        self
+    }
+
+    pub fn group(mut self, groupid: &str, role: LocalRole) -> Self {
+        self.meta.group.push(Group {
+            groupid: groupid.to_string(),
+            role,
+        });
+        self
+    }
+
+    pub fn build_flag(mut self, flag: Flag) -> Self {
+        self.meta.build = Some(flag);
+        self
+    }
+
+    pub fn publish_flag(mut self, flag: Flag) -> Self {
+        self.meta.publish = Some(flag);
+        self
+    }
+
+    pub fn use_for_build_flag(mut self, flag: Flag) -> Self {
+        self.meta.use_for_build = Some(flag);
+        self
+    }
+
+    pub fn debuginfo_flag(mut self, flag: Flag) -> Self {
+        self.meta.debuginfo = Some(flag);
+        self
+    }
+
+    /// Make this maintenance project maintain `project`
+    pub fn maintains(mut self, project: &str) -> Self {
+        self.meta
+            .maintenance
+            .get_or_insert(Maintenance { maintains: vec![] })
+            .maintains
+            .push(Maintains {
+                project: project.to_string(),
+            });
+        self
+    }
+
+    pub fn repository(mut self, repository: RepositoryMeta) -> Self {
+        self.meta.repository.push(repository);
+        self
+    }
+
+    pub fn build(self) -> ProjectMeta {
+        self.meta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{api_fixture, MockResponse, MockServer};
+
+    const JOBS: &str = r#"<jobhistlist>
+  <jobhist package="pkg" rev="3" srcmd5="0123" versrel="1.0-3" bcnt="1" readytime="90" starttime="100" endtime="160" code="succeeded" workerid="w:1"/>
+  <jobhist package="pkg" rev="4" srcmd5="4567" versrel="1.0-4" bcnt="1" code="failed"/>
+</jobhistlist>"#;
+
+    #[tokio::test]
+    async fn job_history_keeps_missing_times_unset() {
+        let server = MockServer::start(|_| MockResponse::new(200).body(JOBS)).await;
+        let project = Project::from_name(Arc::new(server.client()), "prj");
+        let mut filter = JobHistoryFilter::new("repo", BuildArch::X86_64);
+        filter.package.push("pkg".to_string());
+        filter.limit = Some(2);
+        let jobs = project.job_history(&filter).await.unwrap();
+        assert_eq!(
+            server.requests()[0].path,
+            "/build/prj/repo/x86_64/_jobhistory?package=pkg&limit=2"
+        );
+
+        // Oldest first
+        assert_eq!(jobs[0].rev, "3");
+        assert_eq!(jobs[0].time.map(|t| t.unix_timestamp()), Some(160));
+        assert_eq!(jobs[0].duration, Some(Duration::seconds(60)));
+        assert_eq!(jobs[1].code, PackageCode::Failed);
+        assert_eq!(jobs[1].time, None);
+        assert_eq!(jobs[1].duration, None);
+    }
+
+    #[tokio::test]
+    async fn build_commands_send_filter() {
+        let server =
+            MockServer::start(|_| MockResponse::new(200).body("<status code=\"ok\"/>")).await;
+        let project = Project::from_name(Arc::new(server.client()), "prj");
+        let filter = BuildFilter {
+            repository: vec!["openSUSE_Tumbleweed".to_string(), "SLE_15".to_string()],
+            arch: vec![BuildArch::X86_64],
+            package: vec!["pkg".to_string()],
+            code: vec![PackageCode::Failed, PackageCode::Unresolvable],
+        };
+        project.rebuild(&filter).await.unwrap();
+        Package::from_name("pkg".to_string(), project.clone())
+            .wipe(Some("SLE_15"), None)
+            .await
+            .unwrap();
+
+        let paths: Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(
+            paths,
+            [
+                "/build/prj?cmd=rebuild&repository=openSUSE_Tumbleweed&repository=SLE_15\
+                 &arch=x86_64&package=pkg&code=failed&code=unresolvable",
+                "/build/prj?cmd=wipe&repository=SLE_15&package=pkg",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn build_commands_are_not_retried() {
+        let server = MockServer::start(|_| MockResponse::new(502)).await;
+        let project = Project::from_name(Arc::new(server.client()), "prj");
+        let error = project.rebuild(&BuildFilter::default()).await.unwrap_err();
+        assert_eq!(error.http_status(), Some(reqwest::StatusCode::BAD_GATEWAY));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn build_results_list_failed_flavors() {
+        let server =
+            MockServer::start(|_| MockResponse::new(200).body(api_fixture("build_results.xml")))
+                .await;
+        let project = Project::from_name(Arc::new(server.client()), "prj");
+        let filter = BuildFilter {
+            package: vec!["pkg".to_string()],
+            ..Default::default()
+        };
+        let summary = project.build_results(&filter).await.unwrap();
+        assert_eq!(
+            server.requests()[0].path,
+            "/build/prj/_result?view=status&multibuild=1&locallink=1&package=pkg"
+        );
+
+        let tumbleweed = summary
+            .get("openSUSE_Tumbleweed".to_string(), BuildArch::X86_64)
+            .unwrap();
+        assert_eq!(tumbleweed.code(), &RepositoryCode::Building);
+        assert_eq!(tumbleweed.state(), Some(&RepositoryCode::Building));
+        assert!(!tumbleweed.is_dirty());
+        assert!(!tumbleweed.is_packages_ok());
+        assert_eq!(tumbleweed.packages().len(), 3);
+
+        // Published but not yet recomputed by the scheduler
+        let sle = summary
+            .get("SLE_15".to_string(), BuildArch::Aarch64)
+            .unwrap();
+        assert!(sle.is_published());
+        assert_eq!(sle.state(), Some(&RepositoryCode::Finished));
+        assert!(sle.is_dirty());
+
+        let mut failed: Vec<_> = summary
+            .failed_packages()
+            .map(|(repo, arch, p)| (repo, arch.clone(), p.name(), p.flavor(), p.code.clone()))
+            .collect();
+        failed.sort_by_key(|f| (f.0, f.2, f.3));
+        assert_eq!(
+            failed,
+            [
+                (
+                    "SLE_15",
+                    BuildArch::Aarch64,
+                    "pkg",
+                    None,
+                    PackageCode::Unresolvable
+                ),
+                (
+                    "SLE_15",
+                    BuildArch::Aarch64,
+                    "tool",
+                    None,
+                    PackageCode::Broken
+                ),
+                (
+                    "openSUSE_Tumbleweed",
+                    BuildArch::X86_64,
+                    "pkg",
+                    Some("docs"),
+                    PackageCode::Failed
+                ),
+            ]
+        );
+        let docs = tumbleweed.failed_packages().next().unwrap();
+        assert_eq!(docs.details.as_deref(), Some("unable to install texlive"));
+    }
 }
@@ -4,9 +4,17 @@ use crate::{client::OBSClient, error::APIError};
 
 use super::package::Package;
 use super::project::Project;
-use super::xml::request::{Action as XMLAction, Collection, Request as XMLRequest, Target};
+use super::xml::obs::LocalRole;
+use super::xml::request::{
+    Action as XMLAction, Collection, Grouped, History as XMLHistory, Options,
+    Request as XMLRequest, Review as XMLReview, RoleAssignee, State as XMLState, Target,
+};
 
 use itertools::Itertools;
+use time::{
+    format_description::FormatItem, macros::format_description, OffsetDateTime, PrimitiveDateTime,
+    UtcOffset,
+};
 
 #[derive(Debug)]
 pub struct Request {
@@ -14,6 +22,11 @@ pub struct Request {
     pub actions: Vec<Action>,
     pub description: String,
     id: Option<u32>,
+    pub creator: Option<String>,
+    /// Current state, only set on requests read from OBS
+    pub state: Option<RequestStatus>,
+    pub reviews: Vec<Review>,
+    pub history: Vec<HistoryEntry>,
 }
 
 impl Request {
@@ -27,14 +40,37 @@ impl Request {
             actions,
             description,
             id: None,
+            creator: None,
+            state: None,
+            reviews: Vec::new(),
+            history: Vec::new(),
         }
     }
 
+    pub fn id(&self) -> Option<u32> {
+        self.id
+    }
+
+    pub async fn get(client: Arc<OBSClient>, id: u32) -> Result<Self, APIError> {
+        let req = client
+            .get(&["request", &id.to_string()])
+            .query(&[("withhistory", "1")])
+            .build()?;
+        let resp = client.execute(req).await?;
+        let request: XMLRequest =
+            yaserde::de::from_str(&resp.text().await?).map_err(APIError::XMLParseError)?;
+        Ok(Self::from_xml(client, request))
+    }
+
     pub async fn submit(self) -> Result<(), APIError> {
         let body = XMLRequest {
             description: self.description,
             action: self.actions.iter().map_into().collect(),
             id: None,
+            creator: None,
+            state: None,
+            review: Vec::new(),
+            history: Vec::new(),
         };
         let req = self
             .client
@@ -47,55 +83,66 @@ impl Request {
     }
 
     pub async fn mine(client: Arc<OBSClient>) -> Result<Vec<Self>, APIError> {
-        let req = client
-            .get(&["request"])
-            .query(&[
-                ("view", "collection"),
-                ("roles", "creator"),
-                ("states", "new,review"),
-                ("user", client.user()),
-            ])
-            .build()?;
+        let query = RequestQuery::default()
+            .user(client.user())
+            .role(RequestRole::Creator)
+            .state(RequestState::New)
+            .state(RequestState::Review);
+        Self::search(client, &query).await
+    }
+
+    /// Requests matching the query. Queries with time ranges go through the xpath
+    /// search, the others through the request collection.
+    pub async fn search(
+        client: Arc<OBSClient>,
+        query: &RequestQuery,
+    ) -> Result<Vec<Self>, APIError> {
+        let req = match query.has_time_range() {
+            true => client
+                .get(&["search", "request"])
+                .query(&[("match", query.xpath()?)])
+                .query(&query.limit.iter().map(|l| ("limit", l)).collect_vec()),
+            false => client
+                .get(&["request"])
+                .query(&[("view", "collection")])
+                .query(&query.collection_query()),
+        }
+        .build()?;
         let resp = client.execute(req).await?;
         let collection: Collection =
             yaserde::de::from_str(&resp.text().await?).map_err(APIError::XMLParseError)?;
         Ok(collection
             .request
             .into_iter()
-            .map(|r| Request {
-                client: client.clone(),
-                actions: r
-                    .action
-                    .into_iter()
-                    .map(|a| {
-                        let target_package = Package::from_name(
-                            a.target.package,
-                            Project::from_name(client.clone(), &a.target.project),
-                        );
-                        match a._type.as_str() {
-                            "delete" => Action::Delete(target_package),
-                            "submit" => {
-                                let source = a.source.unwrap();
-                                let source_package = Package::from_name(
-                                    source.package,
-                                    Project::from_name(client.clone(), &source.project),
-                                );
-                                Action::Submit {
-                                    source: source_package,
-                                    source_rev: source.rev,
-                                    target: target_package,
-                                }
-                            }
-                            _ => panic!("unknow action type"),
-                        }
-                    })
-                    .collect(),
-                description: r.description,
-                id: r.id,
-            })
+            .map(|r| Self::from_xml(client.clone(), r))
             .collect())
     }
 
+    fn from_xml(client: Arc<OBSClient>, request: XMLRequest) -> Self {
+        Request {
+            actions: request
+                .action
+                .into_iter()
+                .map(|a| Action::from_xml(&client, a))
+                .collect(),
+            description: request.description,
+            id: request.id,
+            creator: request.creator,
+            state: request.state.map(RequestStatus::from),
+            reviews: request
+                .review
+                .into_iter()
+                .filter_map(|r| Review::from_xml(&client, r))
+                .collect(),
+            history: request
+                .history
+                .into_iter()
+                .map(HistoryEntry::from)
+                .collect(),
+            client,
+        }
+    }
+
     pub fn is_for_project(&self, project: &Project) -> bool {
         self.actions.iter().any(|a| a.is_for_project(project))
     }
@@ -111,60 +158,1070 @@ impl Request {
         self.client.execute(req).await?;
         Ok(())
     }
+
+    pub async fn accept(&self, comment: Option<&str>) -> Result<Self, APIError> {
+        self.change_state(RequestState::Accepted, comment, &[])
+            .await
+    }
+
+    pub async fn decline(&self, comment: Option<&str>) -> Result<Self, APIError> {
+        self.change_state(RequestState::Declined, comment, &[])
+            .await
+    }
+
+    /// Withdraw the request, only allowed to its creator
+    pub async fn revoke(&self, comment: Option<&str>) -> Result<Self, APIError> {
+        self.change_state(RequestState::Revoked, comment, &[]).await
+    }
+
+    /// Mark the request as replaced by the request `by`
+    pub async fn supersede(&self, by: u32, comment: Option<&str>) -> Result<Self, APIError> {
+        self.change_state(
+            RequestState::Superseded,
+            comment,
+            &[("superseded_by", by.to_string())],
+        )
+        .await
+    }
+
+    /// Put a declined request back into the `new` state
+    pub async fn reopen(&self, comment: Option<&str>) -> Result<Self, APIError> {
+        self.change_state(RequestState::New, comment, &[]).await
+    }
+
+    pub async fn add_review(&self, by: &ReviewBy, comment: Option<&str>) -> Result<Self, APIError> {
+        self.command("addreview", comment, &by.query()).await
+    }
+
+    pub async fn accept_review(
+        &self,
+        by: &ReviewBy,
+        comment: Option<&str>,
+    ) -> Result<Self, APIError> {
+        self.change_review_state(RequestState::Accepted, by, comment)
+            .await
+    }
+
+    pub async fn decline_review(
+        &self,
+        by: &ReviewBy,
+        comment: Option<&str>,
+    ) -> Result<Self, APIError> {
+        self.change_review_state(RequestState::Declined, by, comment)
+            .await
+    }
+
+    async fn change_state(
+        &self,
+        state: RequestState,
+        comment: Option<&str>,
+        extra: &[(&str, String)],
+    ) -> Result<Self, APIError> {
+        let mut query = vec![("newstate", state.to_string())];
+        query.extend_from_slice(extra);
+        self.command("changestate", comment, &query).await
+    }
+
+    async fn change_review_state(
+        &self,
+        state: RequestState,
+        by: &ReviewBy,
+        comment: Option<&str>,
+    ) -> Result<Self, APIError> {
+        let mut query = vec![("newstate", state.to_string())];
+        query.extend(by.query());
+        self.command("changereviewstate", comment, &query).await
+    }
+
+    /// Run `cmd` on the request and read it back, the commands only answer with a status
+    async fn command(
+        &self,
+        cmd: &str,
+        comment: Option<&str>,
+        query: &[(&str, String)],
+    ) -> Result<Self, APIError> {
+        let id = self.id.ok_or(APIError::InvalidObject)?;
+        let req = self
+            .client
+            .post(&["request", &id.to_string()])
+            .query(&[("cmd", cmd)])
+            .query(query);
+        let req = match comment {
+            Some(comment) => req.query(&[("comment", comment)]),
+            None => req,
+        }
+        .build()?;
+        self.client.execute(req).await?;
+        Self::get(self.client.clone(), id).await
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequestState {
+    New,
+    Review,
+    Accepted,
+    Declined,
+    Revoked,
+    Superseded,
+    Deleted,
+    Unknown,
+}
+
+impl From<&str> for RequestState {
+    fn from(value: &str) -> Self {
+        match value {
+            "new" => Self::New,
+            "review" => Self::Review,
+            "accepted" => Self::Accepted,
+            "declined" => Self::Declined,
+            "revoked" => Self::Revoked,
+            "superseded" => Self::Superseded,
+            "deleted" => Self::Deleted,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl std::fmt::Display for RequestState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::New => "new",
+            Self::Review => "review",
+            Self::Accepted => "accepted",
+            Self::Declined => "declined",
+            Self::Revoked => "revoked",
+            Self::Superseded => "superseded",
+            Self::Deleted => "deleted",
+            Self::Unknown => "unknown",
+        })
+    }
+}
+
+const TIME_FORMAT: &[FormatItem<'static>] =
+    format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]");
+
+/// OBS gives times without offset, in UTC
+fn parse_time(value: &str) -> Option<OffsetDateTime> {
+    PrimitiveDateTime::parse(value, TIME_FORMAT)
+        .ok()
+        .map(PrimitiveDateTime::assume_utc)
+}
+
+fn format_time(value: &OffsetDateTime) -> String {
+    value
+        .to_offset(UtcOffset::UTC)
+        .format(TIME_FORMAT)
+        .unwrap_or_default()
+}
+
+/// Role of the user, group, project or package in the searched requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequestRole {
+    Creator,
+    Maintainer,
+    Reviewer,
+    Source,
+    Target,
+}
+
+impl std::fmt::Display for RequestRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Creator => "creator",
+            Self::Maintainer => "maintainer",
+            Self::Reviewer => "reviewer",
+            Self::Source => "source",
+            Self::Target => "target",
+        })
+    }
+}
+
+/// Filter for [`Request::search`], every criteria set must match
+#[derive(Debug, Clone, Default)]
+pub struct RequestQuery {
+    project: Option<String>,
+    package: Option<String>,
+    user: Option<String>,
+    group: Option<String>,
+    roles: Vec<RequestRole>,
+    states: Vec<RequestState>,
+    types: Vec<String>,
+    created_after: Option<OffsetDateTime>,
+    created_before: Option<OffsetDateTime>,
+    modified_after: Option<OffsetDateTime>,
+    modified_before: Option<OffsetDateTime>,
+    limit: Option<u32>,
+}
+
+impl RequestQuery {
+    pub fn project(mut self, project: &str) -> Self {
+        self.project = Some(project.to_string());
+        self
+    }
+
+    pub fn package(mut self, package: &str) -> Self {
+        self.package = Some(package.to_string());
+        self
+    }
+
+    pub fn user(mut self, user: &str) -> Self {
+        self.user = Some(user.to_string());
+        self
+    }
+
+    pub fn group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
+
+    /// Restrict how the project, package, user or group is involved, any role if unset
+    pub fn role(mut self, role: RequestRole) -> Self {
+        self.roles.push(role);
+        self
+    }
+
+    pub fn state(mut self, state: RequestState) -> Self {
+        self.states.push(state);
+        self
+    }
+
+    /// Only requests with an action of this type, e.g. `submit`
+    pub fn action_type(mut self, action_type: &str) -> Self {
+        self.types.push(action_type.to_string());
+        self
+    }
+
+    pub fn created_after(mut self, time: OffsetDateTime) -> Self {
+        self.created_after = Some(time);
+        self
+    }
+
+    pub fn created_before(mut self, time: OffsetDateTime) -> Self {
+        self.created_before = Some(time);
+        self
+    }
+
+    pub fn modified_after(mut self, time: OffsetDateTime) -> Self {
+        self.modified_after = Some(time);
+        self
+    }
+
+    pub fn modified_before(mut self, time: OffsetDateTime) -> Self {
+        self.modified_before = Some(time);
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn has_time_range(&self) -> bool {
+        self.created_after.is_some()
+            || self.created_before.is_some()
+            || self.modified_after.is_some()
+            || self.modified_before.is_some()
+    }
+
+    fn collection_query(&self) -> Vec<(&'static str, String)> {
+        let mut query: Vec<(&'static str, String)> = [
+            ("project", &self.project),
+            ("package", &self.package),
+            ("user", &self.user),
+            ("group", &self.group),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value.clone()?)))
+        .collect();
+        if !self.roles.is_empty() {
+            query.push(("roles", self.roles.iter().join(",")));
+        }
+        if !self.states.is_empty() {
+            query.push(("states", self.states.iter().join(",")));
+        }
+        if !self.types.is_empty() {
+            query.push(("types", self.types.join(",")));
+        }
+        if let Some(limit) = self.limit {
+            query.push(("limit", limit.to_string()));
+        }
+        query
+    }
+
+    /// Xpath for `/search/request`. The maintainer role can't be expressed there and
+    /// is ignored, a project, package, user or group left without any role is an error.
+    fn xpath(&self) -> Result<String, APIError> {
+        let has_role = |role| self.roles.is_empty() || self.roles.contains(&role);
+        let any_of = |alternatives: Vec<String>| match alternatives.is_empty() {
+            true => None,
+            false => Some(format!("({})", alternatives.join(" or "))),
+        };
+        let involved = |field: &str, alternatives: Vec<String>| {
+            any_of(alternatives).ok_or_else(|| {
+                APIError::InvalidQuery(format!(
+                    "{} can't be searched with roles {}",
+                    field,
+                    self.roles.iter().join(",")
+                ))
+            })
+        };
+        let mut clauses = Vec::new();
+        for (attribute, value) in [("project", &self.project), ("package", &self.package)] {
+            let Some(value) = value else {
+                continue;
+            };
+            let value = xpath_literal(value)?;
+            let mut alternatives = Vec::new();
+            if has_role(RequestRole::Target) {
+                alternatives.push(format!("action/target/@{}={}", attribute, value));
+            }
+            if has_role(RequestRole::Source) {
+                alternatives.push(format!("action/source/@{}={}", attribute, value));
+            }
+            if has_role(RequestRole::Reviewer) {
+                alternatives.push(format!("review/@by_{}={}", attribute, value));
+            }
+            clauses.push(involved(attribute, alternatives)?);
+        }
+        if let Some(user) = &self.user {
+            let user = xpath_literal(user)?;
+            let mut alternatives = Vec::new();
+            if has_role(RequestRole::Creator) {
+                alternatives.push(format!("@creator={}", user));
+            }
+            if has_role(RequestRole::Reviewer) {
+                alternatives.push(format!("review/@by_user={}", user));
+            }
+            clauses.push(involved("user", alternatives)?);
+        }
+        if let Some(group) = &self.group {
+            let group = xpath_literal(group)?;
+            let mut alternatives = Vec::new();
+            if has_role(RequestRole::Reviewer) {
+                alternatives.push(format!("review/@by_group={}", group));
+            }
+            clauses.push(involved("group", alternatives)?);
+        }
+        clauses.extend(any_of(
+            self.states
+                .iter()
+                .map(|s| format!("state/@name='{}'", s))
+                .collect(),
+        ));
+        clauses.extend(any_of(
+            self.types
+                .iter()
+                .map(|t| Ok(format!("action/@type={}", xpath_literal(t)?)))
+                .collect::<Result<_, APIError>>()?,
+        ));
+        for (condition, time) in [
+            ("state/@created>=", &self.created_after),
+            ("state/@created<=", &self.created_before),
+            ("state/@when>=", &self.modified_after),
+            ("state/@when<=", &self.modified_before),
+        ] {
+            if let Some(time) = time {
+                clauses.push(format!("{}'{}'", condition, format_time(time)));
+            }
+        }
+        Ok(clauses.join(" and "))
+    }
+}
+
+/// Quote `value` as an xpath string literal. Xpath has no escape sequences, so a value
+/// can't contain both kinds of quotes.
+fn xpath_literal(value: &str) -> Result<String, APIError> {
+    match (value.contains('\''), value.contains('"')) {
+        (false, _) => Ok(format!("'{}'", value)),
+        (true, false) => Ok(format!("\"{}\"", value)),
+        (true, true) => Err(APIError::InvalidQuery(format!(
+            "{} contains both kinds of quotes",
+            value
+        ))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestStatus {
+    pub state: RequestState,
+    pub who: Option<String>,
+    pub when: Option<OffsetDateTime>,
+    pub comment: Option<String>,
+    pub superseded_by: Option<u32>,
+}
+
+impl From<XMLState> for RequestStatus {
+    fn from(value: XMLState) -> Self {
+        Self {
+            state: value.name.as_str().into(),
+            who: value.who,
+            when: value.when.as_deref().and_then(parse_time),
+            comment: value.comment,
+            superseded_by: value.superseded_by,
+        }
+    }
+}
+
+/// Reviewer of a request
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum ReviewBy {
+    User(String),
+    Group(String),
+    Project(Project),
+    Package(Package),
+}
+
+impl ReviewBy {
+    fn query(&self) -> Vec<(&'static str, String)> {
+        match self {
+            ReviewBy::User(user) => vec![("by_user", user.clone())],
+            ReviewBy::Group(group) => vec![("by_group", group.clone())],
+            ReviewBy::Project(project) => vec![("by_project", project.name())],
+            ReviewBy::Package(package) => vec![
+                ("by_project", package.project.name()),
+                ("by_package", package.name().to_string()),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Review {
+    pub by: ReviewBy,
+    /// `new` while pending, then `accepted` or `declined`
+    pub state: RequestState,
+    /// Who handled the review
+    pub who: Option<String>,
+    pub when: Option<OffsetDateTime>,
+    pub comment: Option<String>,
+}
+
+impl Review {
+    fn from_xml(client: &Arc<OBSClient>, review: XMLReview) -> Option<Self> {
+        let by = match (review.by_user, review.by_group, review.by_project) {
+            (Some(user), _, _) => ReviewBy::User(user),
+            (_, Some(group), _) => ReviewBy::Group(group),
+            (_, _, Some(project)) => {
+                let project = Project::from_name(client.clone(), &project);
+                match review.by_package {
+                    Some(package) => ReviewBy::Package(Package::from_name(package, project)),
+                    None => ReviewBy::Project(project),
+                }
+            }
+            _ => return None,
+        };
+        Some(Self {
+            by,
+            state: review.state.as_str().into(),
+            who: review.who,
+            when: review.when.as_deref().and_then(parse_time),
+            comment: review.comment,
+        })
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.state == RequestState::New
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub who: String,
+    pub when: Option<OffsetDateTime>,
+    pub description: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl From<XMLHistory> for HistoryEntry {
+    fn from(value: XMLHistory) -> Self {
+        Self {
+            who: value.who,
+            when: parse_time(&value.when),
+            description: value.description,
+            comment: value.comment,
+        }
+    }
+}
+
+/// Project or package an action applies to
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum ActionTarget {
+    Project(Project),
+    Package(Package),
+}
+
+impl ActionTarget {
+    pub fn project(&self) -> &Project {
+        match self {
+            ActionTarget::Project(project) => project,
+            ActionTarget::Package(package) => &package.project,
+        }
+    }
+
+    fn from_xml(client: &Arc<OBSClient>, target: &Target) -> Self {
+        let project = Project::from_name(client.clone(), &target.project);
+        match &target.package {
+            Some(package) => Self::Package(Package::from_name(package.clone(), project)),
+            None => Self::Project(project),
+        }
+    }
+
+    fn to_xml(&self) -> Target {
+        match self {
+            ActionTarget::Project(project) => Target {
+                project: project.name(),
+                ..Default::default()
+            },
+            ActionTarget::Package(package) => package_target(package),
+        }
+    }
+}
+
+impl From<Package> for ActionTarget {
+    fn from(value: Package) -> Self {
+        Self::Package(value)
+    }
+}
+
+impl From<Project> for ActionTarget {
+    fn from(value: Project) -> Self {
+        Self::Project(value)
+    }
+}
+
+fn package_target(package: &Package) -> Target {
+    Target {
+        project: package.project.name(),
+        package: Some(package.name().to_string()),
+        ..Default::default()
+    }
+}
+
+/// User or group given a role by `add_role` and `set_bugowner`
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum Assignee {
+    Person(String),
+    Group(String),
+}
+
+/// What happens to the source package once a submit is accepted
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum SourceUpdate {
+    /// Sync the source package with the target
+    Update,
+    /// Delete the source package
+    Cleanup,
+    NoUpdate,
+}
+
+impl SourceUpdate {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SourceUpdate::Update => "update",
+            SourceUpdate::Cleanup => "cleanup",
+            SourceUpdate::NoUpdate => "noupdate",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "update" => Some(Self::Update),
+            "cleanup" => Some(Self::Cleanup),
+            "noupdate" => Some(Self::NoUpdate),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct ActionOptions {
+    pub sourceupdate: Option<SourceUpdate>,
+    /// Update the link in the target to point to the submitted sources
+    pub updatelink: bool,
+    /// Make the origin of the target package older than the source
+    pub makeoriginolder: bool,
+}
+
+impl ActionOptions {
+    fn from_xml(options: Option<&Options>) -> Self {
+        match options {
+            Some(options) => Self {
+                sourceupdate: options
+                    .sourceupdate
+                    .as_deref()
+                    .and_then(SourceUpdate::from_str),
+                updatelink: options.updatelink.unwrap_or_default(),
+                makeoriginolder: options.makeoriginolder.unwrap_or_default(),
+            },
+            None => Self::default(),
+        }
+    }
+
+    fn to_xml(&self) -> Option<Options> {
+        if *self == Self::default() {
+            return None;
+        }
+        Some(Options {
+            sourceupdate: self.sourceupdate.map(|s| s.as_str().to_string()),
+            updatelink: self.updatelink.then_some(true),
+            makeoriginolder: self.makeoriginolder.then_some(true),
+        })
+    }
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum Action {
     Submit {
         source: Package,
-        source_rev: Option<u32>,
+        /// Revision number or srcmd5 of the source package
+        source_rev: Option<String>,
         target: Package,
+        options: ActionOptions,
+    },
+    Delete(ActionTarget),
+    AddRole {
+        target: ActionTarget,
+        assignee: Assignee,
+        role: LocalRole,
+    },
+    SetBugowner {
+        target: ActionTarget,
+        assignee: Assignee,
+    },
+    /// Make `source` the devel package of `target`
+    ChangeDevel {
+        source: Package,
+        target: Package,
+    },
+    MaintenanceIncident {
+        source: ActionTarget,
+        /// Maintenance project the incident is created in
+        target: Project,
+        release_project: Option<Project>,
+        options: ActionOptions,
+    },
+    MaintenanceRelease {
+        source: Package,
+        target: Package,
+    },
+    Release {
+        source: Package,
+        target: ActionTarget,
+        repository: Option<String>,
+    },
+    /// Ids of the requests grouped together
+    Group(Vec<u32>),
+    /// Action type not known by this client. The source and target are read from
+    /// `raw`, which is sent back unchanged.
+    Unknown {
+        action_type: String,
+        source: Option<ActionTarget>,
+        target: Option<ActionTarget>,
+        raw: RawAction,
     },
-    Delete(Package),
 }
 
+/// Action as received from OBS, with the persons, groups and options this client
+/// doesn't interpret
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct RawAction(Box<XMLAction>);
+
 impl From<&Action> for XMLAction {
     fn from(value: &Action) -> Self {
+        let action = |action_type: &str| Self {
+            _type: action_type.to_string(),
+            source: None,
+            target: None,
+            person: None,
+            group: None,
+            options: None,
+            grouped: Vec::new(),
+        };
         match value {
-            Action::Delete(p) => Self {
-                _type: "delete".to_string(),
-                source: None,
-                target: Target {
-                    package: p.name().to_string(),
-                    project: p.project.name(),
-                    rev: None,
-                },
-            },
             Action::Submit {
                 source,
                 source_rev,
                 target,
+                options,
             } => Self {
-                _type: "submit".to_string(),
                 source: Some(Target {
-                    package: source.name().to_string(),
-                    project: source.project.name(),
-                    rev: *source_rev,
+                    rev: source_rev.clone(),
+                    ..package_target(source)
                 }),
-                target: Target {
-                    rev: None,
-                    package: target.name().to_string(),
-                    project: target.project.name(),
-                },
+                target: Some(package_target(target)),
+                options: options.to_xml(),
+                ..action("submit")
+            },
+            Action::Delete(target) => Self {
+                target: Some(target.to_xml()),
+                ..action("delete")
+            },
+            Action::AddRole {
+                target,
+                assignee,
+                role,
+            } => {
+                let (person, group) = assignee_xml(assignee, Some(role.clone()));
+                Self {
+                    target: Some(target.to_xml()),
+                    person,
+                    group,
+                    ..action("add_role")
+                }
+            }
+            Action::SetBugowner { target, assignee } => {
+                let (person, group) = assignee_xml(assignee, None);
+                Self {
+                    target: Some(target.to_xml()),
+                    person,
+                    group,
+                    ..action("set_bugowner")
+                }
+            }
+            Action::ChangeDevel { source, target } => Self {
+                source: Some(package_target(source)),
+                target: Some(package_target(target)),
+                ..action("change_devel")
+            },
+            Action::MaintenanceIncident {
+                source,
+                target,
+                release_project,
+                options,
+            } => Self {
+                source: Some(source.to_xml()),
+                target: Some(Target {
+                    project: target.name(),
+                    releaseproject: release_project.as_ref().map(Project::name),
+                    ..Default::default()
+                }),
+                options: options.to_xml(),
+                ..action("maintenance_incident")
+            },
+            Action::MaintenanceRelease { source, target } => Self {
+                source: Some(package_target(source)),
+                target: Some(package_target(target)),
+                ..action("maintenance_release")
             },
+            Action::Release {
+                source,
+                target,
+                repository,
+            } => Self {
+                source: Some(package_target(source)),
+                target: Some(Target {
+                    repository: repository.clone(),
+                    ..target.to_xml()
+                }),
+                ..action("release")
+            },
+            Action::Group(ids) => Self {
+                grouped: ids.iter().map(|&id| Grouped { id }).collect(),
+                ..action("group")
+            },
+            Action::Unknown { raw, .. } => raw.0.as_ref().clone(),
         }
     }
 }
 
+fn assignee_xml(
+    assignee: &Assignee,
+    role: Option<LocalRole>,
+) -> (Option<RoleAssignee>, Option<RoleAssignee>) {
+    match assignee {
+        Assignee::Person(name) => (
+            Some(RoleAssignee {
+                name: name.clone(),
+                role,
+            }),
+            None,
+        ),
+        Assignee::Group(name) => (
+            None,
+            Some(RoleAssignee {
+                name: name.clone(),
+                role,
+            }),
+        ),
+    }
+}
+
 impl Action {
-    pub fn is_for_project(&self, project: &Project) -> bool {
+    fn from_xml(client: &Arc<OBSClient>, action: XMLAction) -> Self {
+        use ActionTarget::Package as P;
+
+        let source = action
+            .source
+            .as_ref()
+            .map(|t| ActionTarget::from_xml(client, t));
+        let target = action
+            .target
+            .as_ref()
+            .map(|t| ActionTarget::from_xml(client, t));
+        let assignee = action
+            .person
+            .as_ref()
+            .map(|p| (Assignee::Person(p.name.clone()), p.role.clone()))
+            .or_else(|| {
+                action
+                    .group
+                    .as_ref()
+                    .map(|g| (Assignee::Group(g.name.clone()), g.role.clone()))
+            });
+        let options = ActionOptions::from_xml(action.options.as_ref());
+        let typed = match (action._type.as_str(), source.clone(), target.clone()) {
+            ("submit", Some(P(source)), Some(P(target))) => Some(Action::Submit {
+                source,
+                source_rev: action.source.as_ref().and_then(|s| s.rev.clone()),
+                target,
+                options,
+            }),
+            ("delete", _, Some(target)) => Some(Action::Delete(target)),
+            ("add_role", _, Some(target)) => match assignee {
+                Some((assignee, Some(role))) => Some(Action::AddRole {
+                    target,
+                    assignee,
+                    role,
+                }),
+                _ => None,
+            },
+            ("set_bugowner", _, Some(target)) => {
+                assignee.map(|(assignee, _)| Action::SetBugowner { target, assignee })
+            }
+            ("change_devel", Some(P(source)), Some(P(target))) => {
+                Some(Action::ChangeDevel { source, target })
+            }
+            ("maintenance_incident", Some(source), Some(target)) => {
+                Some(Action::MaintenanceIncident {
+                    source,
+                    target: target.project().clone(),
+                    release_project: action
+                        .target
+                        .as_ref()
+                        .and_then(|t| t.releaseproject.as_deref())
+                        .map(|p| Project::from_name(client.clone(), p)),
+                    options,
+                })
+            }
+            ("maintenance_release", Some(P(source)), Some(P(target))) => {
+                Some(Action::MaintenanceRelease { source, target })
+            }
+            ("release", Some(P(source)), Some(target)) => Some(Action::Release {
+                source,
+                target,
+                repository: action.target.as_ref().and_then(|t| t.repository.clone()),
+            }),
+            ("group", _, _) => Some(Action::Group(action.grouped.iter().map(|g| g.id).collect())),
+            _ => None,
+        };
+        typed.unwrap_or_else(|| Action::Unknown {
+            action_type: action._type.clone(),
+            source,
+            target,
+            raw: RawAction(Box::new(action)),
+        })
+    }
+
+    /// Project the action applies to, `None` for groups
+    pub fn target_project(&self) -> Option<&Project> {
         match self {
-            Action::Delete(p) => p.project == *project,
-            Action::Submit {
-                source: _,
-                source_rev: _,
+            Action::Submit { target, .. }
+            | Action::ChangeDevel { target, .. }
+            | Action::MaintenanceRelease { target, .. } => Some(&target.project),
+            Action::Delete(target)
+            | Action::AddRole { target, .. }
+            | Action::SetBugowner { target, .. }
+            | Action::Release { target, .. } => Some(target.project()),
+            Action::MaintenanceIncident { target, .. } => Some(target),
+            Action::Group(_) => None,
+            Action::Unknown { target, .. } => target.as_ref().map(ActionTarget::project),
+        }
+    }
+
+    pub fn is_for_project(&self, project: &Project) -> bool {
+        self.target_project() == Some(project)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{MockResponse, MockServer};
+
+    const REQUEST: &str = r#"<request id="42" creator="someone">
+  <action type="submit">
+    <source project="home:someone" package="pkg" rev="0123456789abcdef0123456789abcdef"/>
+    <target project="prj" package="pkg"/>
+  </action>
+  <action type="future_action">
+    <source project="home:someone" package="pkg"/>
+    <target project="prj"/>
+    <person name="someone" role="maintainer"/>
+    <group name="reviewers" role="reviewer"/>
+    <options>
+      <sourceupdate>cleanup</sourceupdate>
+    </options>
+    <grouped id="41"/>
+  </action>
+  <state name="new" who="someone" when="2024-01-01T00:00:00"/>
+  <description>Update</description>
+</request>"#;
+
+    #[tokio::test]
+    async fn actions_keep_what_obs_sent() {
+        let server = MockServer::start(|_| MockResponse::new(200).body(REQUEST)).await;
+        let request = Request::get(Arc::new(server.client()), 42).await.unwrap();
+        let sent: XMLRequest = yaserde::de::from_str(REQUEST).unwrap();
+
+        match &request.actions[0] {
+            Action::Submit { source_rev, .. } => assert_eq!(
+                source_rev.as_deref(),
+                Some("0123456789abcdef0123456789abcdef")
+            ),
+            other => panic!("expected a submit, got {:?}", other),
+        }
+        assert_eq!(XMLAction::from(&request.actions[0]), sent.action[0]);
+
+        match &request.actions[1] {
+            Action::Unknown {
+                action_type,
                 target,
-            } => target.project == *project,
+                ..
+            } => {
+                assert_eq!(action_type, "future_action");
+                assert_eq!(target.as_ref().unwrap().project().name(), "prj");
+            }
+            other => panic!("expected an unknown action, got {:?}", other),
         }
+        assert_eq!(XMLAction::from(&request.actions[1]), sent.action[1]);
+    }
+
+    #[tokio::test]
+    async fn commands_send_state_and_reviewer_then_reload() {
+        let server = MockServer::start(|req| match req.method.as_str() {
+            "GET" => MockResponse::new(200).body(REQUEST),
+            _ => MockResponse::new(200).body("<status code=\"ok\"/>"),
+        })
+        .await;
+        let client = Arc::new(server.client());
+        let request = Request::get(client.clone(), 42).await.unwrap();
+        let project = Project::from_name(client, "prj");
+        let package = Package::from_name("pkg".to_string(), project.clone());
+
+        request.accept(Some("Looks good")).await.unwrap();
+        request.decline(None).await.unwrap();
+        request.revoke(None).await.unwrap();
+        request.supersede(43, None).await.unwrap();
+        let reopened = request.reopen(None).await.unwrap();
+        request
+            .add_review(&ReviewBy::User("someone".to_string()), None)
+            .await
+            .unwrap();
+        request
+            .add_review(&ReviewBy::Package(package), None)
+            .await
+            .unwrap();
+        request
+            .accept_review(&ReviewBy::Group("reviewers".to_string()), None)
+            .await
+            .unwrap();
+        request
+            .decline_review(&ReviewBy::Project(project), Some("Wrong target"))
+            .await
+            .unwrap();
+        assert_eq!(reopened.id, Some(42));
+        assert_eq!(reopened.description, "Update");
+
+        let requests = server.requests();
+        // Every command is followed by reading the request back
+        for pair in requests[1..].chunks(2) {
+            assert_eq!(pair[0].method, "POST");
+            assert_eq!(
+                (pair[1].method.as_str(), pair[1].path.as_str()),
+                ("GET", "/request/42?withhistory=1")
+            );
+        }
+        let commands: Vec<&str> = requests[1..]
+            .iter()
+            .step_by(2)
+            .map(|r| r.path.as_str())
+            .collect();
+        assert_eq!(
+            commands,
+            [
+                "/request/42?cmd=changestate&newstate=accepted&comment=Looks+good",
+                "/request/42?cmd=changestate&newstate=declined",
+                "/request/42?cmd=changestate&newstate=revoked",
+                "/request/42?cmd=changestate&newstate=superseded&superseded_by=43",
+                "/request/42?cmd=changestate&newstate=new",
+                "/request/42?cmd=addreview&by_user=someone",
+                "/request/42?cmd=addreview&by_project=prj&by_package=pkg",
+                "/request/42?cmd=changereviewstate&newstate=accepted&by_group=reviewers",
+                "/request/42?cmd=changereviewstate&newstate=declined&by_project=prj\
+                 &comment=Wrong+target",
+            ]
+        );
+    }
+
+    fn epoch() -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(0).unwrap()
+    }
+
+    #[test]
+    fn xpath_matches_fields_by_role() {
+        let query = RequestQuery::default()
+            .project("prj")
+            .user("someone")
+            .group("reviewers")
+            .role(RequestRole::Target)
+            .role(RequestRole::Reviewer)
+            .state(RequestState::New)
+            .action_type("submit")
+            .created_after(epoch());
+        assert_eq!(
+            query.xpath().unwrap(),
+            format!(
+                "(action/target/@project='prj' or review/@by_project='prj') \
+                 and (review/@by_user='someone') \
+                 and (review/@by_group='reviewers') \
+                 and (state/@name='new') \
+                 and (action/@type='submit') \
+                 and state/@created>='{}'",
+                format_time(&epoch())
+            )
+        );
+    }
+
+    #[test]
+    fn xpath_rejects_fields_without_roles() {
+        let project = RequestQuery::default()
+            .project("prj")
+            .role(RequestRole::Creator)
+            .created_after(epoch());
+        assert!(matches!(project.xpath(), Err(APIError::InvalidQuery(_))));
+
+        let user = RequestQuery::default()
+            .user("someone")
+            .role(RequestRole::Maintainer)
+            .created_after(epoch());
+        assert!(matches!(user.xpath(), Err(APIError::InvalidQuery(_))));
+
+        let group = RequestQuery::default()
+            .group("reviewers")
+            .role(RequestRole::Creator)
+            .created_after(epoch());
+        assert!(matches!(group.xpath(), Err(APIError::InvalidQuery(_))));
+    }
+
+    #[test]
+    fn xpath_quotes_values() {
+        let query = RequestQuery::default()
+            .package("it's")
+            .role(RequestRole::Source);
+        assert_eq!(query.xpath().unwrap(), r#"(action/source/@package="it's")"#);
+
+        let query = RequestQuery::default().user(r#"it's "quoted""#);
+        assert!(matches!(query.xpath(), Err(APIError::InvalidQuery(_))));
     }
 }
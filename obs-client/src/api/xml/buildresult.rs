@@ -14,6 +14,11 @@ pub struct RepositoryResult {
     pub arch: BuildArch,
     #[yaserde(attribute)]
     pub code: RepositoryCode,
+    #[yaserde(attribute)]
+    pub state: Option<RepositoryCode>,
+    /// Set while the scheduler still has to recompute the repository state
+    #[yaserde(attribute)]
+    pub dirty: Option<bool>,
     pub summary: Option<Summary>,
     pub status: Vec<PackageStatus>,
     pub binarylist: Vec<PackageBinaryList>,
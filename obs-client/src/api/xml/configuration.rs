@@ -0,0 +1,41 @@
+use yaserde::YaDeserialize;
+
+#[derive(Debug, Clone, YaDeserialize)]
+#[yaserde(rename = "configuration")]
+pub struct Configuration {
+    pub title: String,
+    pub description: String,
+    pub name: Option<String>,
+    pub download_on_demand: Option<String>,
+    pub enforce_project_keys: Option<String>,
+    pub anonymous: Option<String>,
+    pub registration: Option<String>,
+    pub default_access_disabled: Option<String>,
+    pub allow_user_to_create_home_project: Option<String>,
+    pub disallow_group_creation: Option<String>,
+    pub change_password: Option<String>,
+    pub hide_private_options: Option<String>,
+    pub gravatar: Option<String>,
+    pub download_url: Option<String>,
+    pub obs_url: Option<String>,
+    pub api_url: Option<String>,
+    pub ymp_url: Option<String>,
+    pub bugzilla_url: Option<String>,
+    pub http_proxy: Option<String>,
+    pub no_proxy: Option<String>,
+    pub theme: Option<String>,
+    pub cleanup_after_days: Option<String>,
+    pub cleanup_empty_projects: Option<String>,
+    pub disable_publish_for_branches: Option<String>,
+    pub admin_email: Option<String>,
+    pub unlisted_projects_filter: Option<String>,
+    pub unlisted_projects_filter_description: Option<String>,
+    pub tos_url: Option<String>,
+    pub code_of_conduct: Option<String>,
+    pub schedulers: Option<Schedulers>,
+}
+
+#[derive(Debug, Clone, YaDeserialize)]
+pub struct Schedulers {
+    pub arch: Vec<String>,
+}
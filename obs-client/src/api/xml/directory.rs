@@ -0,0 +1,63 @@
+use yaserde::{YaDeserialize, YaSerialize};
+
+#[derive(Debug, Clone, YaDeserialize)]
+#[yaserde(rename = "directory")]
+pub struct Directory {
+    /// Set to `missing` by `commitfilelist` when file contents must be uploaded first
+    #[yaserde(attribute)]
+    pub error: Option<String>,
+    #[yaserde(attribute)]
+    pub rev: Option<String>,
+    #[yaserde(attribute)]
+    pub vrev: Option<String>,
+    #[yaserde(attribute)]
+    pub srcmd5: Option<String>,
+    pub linkinfo: Option<LinkInfo>,
+    pub entry: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, YaDeserialize)]
+pub struct LinkInfo {
+    #[yaserde(attribute)]
+    pub project: String,
+    #[yaserde(attribute)]
+    pub package: String,
+    #[yaserde(attribute)]
+    pub srcmd5: Option<String>,
+    #[yaserde(attribute)]
+    pub baserev: Option<String>,
+    #[yaserde(attribute)]
+    pub xsrcmd5: Option<String>,
+    #[yaserde(attribute)]
+    pub lsrcmd5: Option<String>,
+    #[yaserde(attribute)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, YaDeserialize)]
+pub struct Entry {
+    #[yaserde(attribute)]
+    pub name: String,
+    #[yaserde(attribute)]
+    pub md5: Option<String>,
+    #[yaserde(attribute)]
+    pub size: Option<u64>,
+    #[yaserde(attribute)]
+    pub mtime: Option<i64>,
+}
+
+/// File list sent to `cmd=commitfilelist`
+#[derive(Debug, Clone, YaSerialize)]
+#[yaserde(rename = "directory")]
+pub struct FileList {
+    pub entry: Vec<FileListEntry>,
+}
+
+#[derive(Debug, Clone, YaSerialize)]
+#[yaserde(rename = "entry")]
+pub struct FileListEntry {
+    #[yaserde(attribute)]
+    pub name: String,
+    #[yaserde(attribute)]
+    pub md5: String,
+}
@@ -0,0 +1,56 @@
+use yaserde::YaDeserialize;
+
+use super::obs::PackageCode;
+
+#[derive(Debug, Clone, YaDeserialize)]
+#[yaserde(rename = "buildhistory")]
+pub struct BuildHistory {
+    pub entry: Vec<BuildHistoryEntry>,
+}
+
+#[derive(Debug, Clone, YaDeserialize)]
+pub struct BuildHistoryEntry {
+    #[yaserde(attribute)]
+    pub rev: String,
+    #[yaserde(attribute)]
+    pub srcmd5: String,
+    #[yaserde(attribute)]
+    pub versrel: String,
+    #[yaserde(attribute)]
+    pub bcnt: u32,
+    #[yaserde(attribute)]
+    pub time: i64,
+    /// Build time in seconds, missing on entries written by older OBS versions
+    #[yaserde(attribute)]
+    pub duration: Option<u64>,
+}
+
+#[derive(Debug, Clone, YaDeserialize)]
+#[yaserde(rename = "jobhistlist")]
+pub struct JobHistoryList {
+    pub jobhist: Vec<JobHistory>,
+}
+
+#[derive(Debug, Clone, YaDeserialize)]
+pub struct JobHistory {
+    #[yaserde(attribute)]
+    pub package: String,
+    #[yaserde(attribute)]
+    pub rev: String,
+    #[yaserde(attribute)]
+    pub srcmd5: String,
+    #[yaserde(attribute)]
+    pub versrel: String,
+    #[yaserde(attribute)]
+    pub bcnt: u32,
+    #[yaserde(attribute)]
+    pub starttime: Option<i64>,
+    #[yaserde(attribute)]
+    pub endtime: Option<i64>,
+    #[yaserde(attribute)]
+    pub code: PackageCode,
+    #[yaserde(attribute)]
+    pub workerid: Option<String>,
+    #[yaserde(attribute)]
+    pub reason: Option<String>,
+}
@@ -1,7 +1,12 @@
 pub(crate) mod buildepinfo;
 pub(crate) mod buildinfo;
 pub(crate) mod buildresult;
+pub(crate) mod configuration;
+pub(crate) mod directory;
+pub(crate) mod history;
 pub(crate) mod obs;
+pub(crate) mod package;
 pub(crate) mod project;
 pub(crate) mod repository;
 pub(crate) mod request;
+pub(crate) mod status;
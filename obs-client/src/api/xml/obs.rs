@@ -24,6 +24,9 @@ pub enum PackageCode {
     Building,
     #[yaserde(rename = "finished")]
     Finished,
+    /// Job result when the rebuild produced the same binaries
+    #[yaserde(rename = "unchanged")]
+    Unchanged,
 }
 
 impl Default for PackageCode {
@@ -32,6 +35,13 @@ impl Default for PackageCode {
     }
 }
 
+impl std::fmt::Display for PackageCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let variant = to_snake_case(&format!("{:?}", self));
+        f.write_str(&variant)
+    }
+}
+
 impl PackageCode {
     pub fn is_ok(&self) -> bool {
         [
@@ -41,6 +51,15 @@ impl PackageCode {
         ]
         .contains(self)
     }
+
+    pub fn is_failed(&self) -> bool {
+        [
+            PackageCode::Failed,
+            PackageCode::Unresolvable,
+            PackageCode::Broken,
+        ]
+        .contains(self)
+    }
 }
 
 #[derive(Debug, Clone, yaserde::YaDeserialize, PartialEq, Eq, Hash)]
@@ -87,7 +106,7 @@ impl From<&str> for RepositoryCode {
     }
 }
 
-#[derive(Debug, Clone, yaserde::YaDeserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, yaserde::YaDeserialize, yaserde::YaSerialize, PartialEq, Eq, Hash)]
 pub enum BuildArch {
     #[yaserde(rename = "noarch")]
     Noarch,
@@ -215,19 +234,37 @@ fn to_snake_case(s: &str) -> String {
     snake
 }
 
-#[derive(Debug, Clone, yaserde::YaDeserialize)]
+#[derive(Debug, Clone, yaserde::YaDeserialize, yaserde::YaSerialize)]
 pub struct SimpleFlag {
-    pub enable: Option<String>,
-    pub disable: Option<String>,
+    pub enable: Option<FlagSwitch>,
+    pub disable: Option<FlagSwitch>,
 }
 
-#[derive(Debug, Clone, yaserde::YaDeserialize)]
+#[derive(Debug, Clone, Default, yaserde::YaDeserialize, yaserde::YaSerialize)]
 pub struct Flag {
     pub enable: Vec<FlagSwitch>,
     pub disable: Vec<FlagSwitch>,
 }
 
-#[derive(Debug, Clone, yaserde::YaDeserialize)]
+impl Flag {
+    /// Enabled for every repository and architecture
+    pub fn enabled() -> Self {
+        Self {
+            enable: vec![FlagSwitch::default()],
+            disable: vec![],
+        }
+    }
+
+    /// Disabled for every repository and architecture
+    pub fn disabled() -> Self {
+        Self {
+            enable: vec![],
+            disable: vec![FlagSwitch::default()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, yaserde::YaDeserialize, yaserde::YaSerialize)]
 pub struct FlagSwitch {
     #[yaserde(attribute)]
     pub repository: Option<String>,
@@ -235,7 +272,7 @@ pub struct FlagSwitch {
     pub arch: Option<BuildArch>,
 }
 
-#[derive(Debug, Clone, yaserde::YaDeserialize)]
+#[derive(Debug, Clone, yaserde::YaDeserialize, yaserde::YaSerialize)]
 pub struct Group {
     #[yaserde(attribute)]
     pub groupid: String,
@@ -243,7 +280,7 @@ pub struct Group {
     pub role: LocalRole,
 }
 
-#[derive(Debug, Clone, yaserde::YaDeserialize)]
+#[derive(Debug, Clone, yaserde::YaDeserialize, yaserde::YaSerialize)]
 pub struct Person {
     #[yaserde(attribute)]
     pub userid: String,
@@ -251,7 +288,7 @@ pub struct Person {
     pub role: LocalRole,
 }
 
-#[derive(Debug, Clone, yaserde::YaDeserialize)]
+#[derive(Debug, Clone, yaserde::YaDeserialize, yaserde::YaSerialize, PartialEq, Eq, Hash)]
 pub enum LocalRole {
     #[yaserde(rename = "maintainer")]
     Maintainer,
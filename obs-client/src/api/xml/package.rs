@@ -0,0 +1,51 @@
+use yaserde::{YaDeserialize, YaSerialize};
+
+use super::obs::{Flag, Group, Person, SimpleFlag};
+
+#[derive(Debug, Clone, Default, YaDeserialize, YaSerialize)]
+#[yaserde(rename = "package")]
+pub struct Package {
+    #[yaserde(attribute)]
+    pub name: Option<String>,
+    #[yaserde(attribute)]
+    pub project: Option<String>,
+    pub title: String,
+    pub description: String,
+    pub devel: Option<DevelPackage>,
+    #[yaserde(rename = "releasename")]
+    pub release_name: Option<String>,
+    pub person: Vec<Person>,
+    pub group: Vec<Group>,
+    pub lock: Option<SimpleFlag>,
+    pub build: Option<Flag>,
+    pub publish: Option<Flag>,
+    #[yaserde(rename = "useforbuild")]
+    pub use_for_build: Option<Flag>,
+    pub debuginfo: Option<Flag>,
+    #[yaserde(rename = "binarydownload")]
+    pub binary_download: Option<Flag>,
+    #[yaserde(rename = "sourceaccess")]
+    pub source_access: Option<SimpleFlag>,
+    pub url: Option<String>,
+    pub scmsync: Option<String>,
+    #[yaserde(rename = "bcntsynctag")]
+    pub bcnt_sync_tag: Option<String>,
+}
+
+#[derive(Debug, Clone, YaDeserialize, YaSerialize)]
+pub struct DevelPackage {
+    #[yaserde(attribute)]
+    pub project: Option<String>,
+    #[yaserde(attribute)]
+    pub package: Option<String>,
+}
+
+/// Content of the `_link` file
+#[derive(Debug, Clone, YaSerialize)]
+#[yaserde(rename = "link")]
+pub struct Link {
+    #[yaserde(attribute)]
+    pub project: String,
+    #[yaserde(attribute)]
+    pub package: String,
+}
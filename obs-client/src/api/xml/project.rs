@@ -1,18 +1,19 @@
-use yaserde::YaDeserialize;
+use yaserde::{YaDeserialize, YaSerialize};
 
 use super::{
     obs::{Flag, Group, Person, SimpleFlag},
     repository::Repository,
 };
 
-#[derive(Debug, Clone, YaDeserialize)]
+#[derive(Debug, Clone, Default, YaDeserialize, YaSerialize)]
+#[yaserde(rename = "project")]
 pub struct Project {
     #[yaserde(attribute)]
     pub name: Option<String>,
     #[yaserde(attribute)]
     pub kind: Option<ProjectKind>,
-    pub title: Option<String>,
-    pub description: Option<String>,
+    pub title: String,
+    pub description: String,
     pub url: Option<String>,
     pub link: Vec<ProjectLink>,
     #[yaserde(rename = "mountproject")]
@@ -21,8 +22,8 @@ pub struct Project {
     pub remote_url: Option<String>,
     pub scmsync: Option<String>,
     pub devel: Option<DevelProject>,
-    pub group: Vec<Group>,
     pub person: Vec<Person>,
+    pub group: Vec<Group>,
     pub lock: Option<SimpleFlag>,
     pub build: Option<Flag>,
     pub publish: Option<Flag>,
@@ -38,7 +39,7 @@ pub struct Project {
     pub repository: Vec<Repository>,
 }
 
-#[derive(Debug, Clone, YaDeserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, YaDeserialize, YaSerialize, PartialEq, Eq)]
 pub enum ProjectKind {
     #[yaserde(rename = "standard")]
     Standard,
@@ -55,44 +56,42 @@ impl Default for ProjectKind {
     }
 }
 
-#[derive(Debug, Clone, YaDeserialize)]
+#[derive(Debug, Clone, YaDeserialize, YaSerialize)]
 pub struct Maintenance {
     pub maintains: Vec<Maintains>,
 }
 
-#[derive(Debug, Clone, YaDeserialize)]
+#[derive(Debug, Clone, YaDeserialize, YaSerialize)]
 pub struct Maintains {
     #[yaserde(attribute)]
     pub project: String,
 }
 
-#[derive(Debug, Clone, YaDeserialize)]
+#[derive(Debug, Clone, YaDeserialize, YaSerialize)]
 pub struct DevelProject {
     #[yaserde(attribute)]
     pub project: String,
 }
 
-#[derive(Debug, Clone, YaDeserialize)]
+#[derive(Debug, Clone, YaDeserialize, YaSerialize)]
 pub struct ProjectLink {
     #[yaserde(attribute)]
     pub project: String,
     #[yaserde(attribute)]
-    pub vrevmode: VrevMode,
+    pub vrevmode: Option<VrevMode>,
 }
 
-#[derive(Debug, Clone, YaDeserialize)]
+#[derive(Debug, Clone, Default, YaDeserialize, YaSerialize)]
 pub enum VrevMode {
+    #[default]
+    #[yaserde(rename = "standard")]
     Standard,
+    #[yaserde(rename = "extend")]
     Extend,
+    #[yaserde(rename = "unextend")]
     Unextend,
 }
 
-impl Default for VrevMode {
-    fn default() -> Self {
-        Self::Standard
-    }
-}
-
 #[derive(Debug, Clone, YaDeserialize)]
 pub struct SourceInfoList {
     pub sourceinfo: Vec<SourceInfo>,
@@ -136,3 +135,116 @@ pub struct PackageLink {
     #[yaserde(attribute)]
     pub package: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::xml::obs::{BuildArch, LocalRole};
+    use crate::api::xml::repository::{
+        BlockMode, LinkedBuildMode, RebuildMode, ReleaseTrigger, RepoType,
+    };
+    use crate::test_utils::xml_events;
+
+    fn fixture(name: &str) -> String {
+        std::fs::read_to_string(format!(
+            "{}/tests/fixtures/meta/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        ))
+        .unwrap()
+    }
+
+    /// Parse the fixture, check it serializes back to the same document and parses again
+    fn round_trip(name: &str) -> Project {
+        let input = fixture(name);
+        let project: Project = yaserde::de::from_str(&input).unwrap();
+        let output = yaserde::ser::to_string(&project).unwrap();
+        assert_eq!(xml_events(&output), xml_events(&input), "{}", output);
+        yaserde::de::from_str(&output).unwrap()
+    }
+
+    #[test]
+    fn standard_project() {
+        let project = round_trip("project_standard.xml");
+        assert_eq!(project.name.as_deref(), Some("openSUSE:Factory"));
+        assert_eq!(project.kind, None);
+        assert!(matches!(project.link[0].vrevmode, Some(VrevMode::Extend)));
+        assert_eq!(project.devel.unwrap().project, "openSUSE:Factory:Staging");
+        assert_eq!(project.person[1].role, LocalRole::Reviewer);
+        assert!(project.lock.unwrap().disable.is_some());
+
+        let build = project.build.unwrap();
+        assert_eq!(build.enable[0].repository.as_deref(), Some("standard"));
+        assert!(matches!(build.enable[0].arch, Some(BuildArch::X86_64)));
+        assert_eq!(build.disable.len(), 2);
+        assert!(matches!(build.disable[1].arch, Some(BuildArch::I586)));
+
+        let standard = &project.repository[0];
+        assert!(matches!(standard.rebuild, Some(RebuildMode::Local)));
+        assert!(matches!(standard.block, Some(BlockMode::Never)));
+        assert!(matches!(
+            standard.linkedbuild,
+            Some(LinkedBuildMode::Localdep)
+        ));
+        assert_eq!(
+            standard.releasetarget[0].trigger,
+            Some(ReleaseTrigger::Manual)
+        );
+        let paths: Vec<&str> = standard.path.iter().map(|p| p.project.as_str()).collect();
+        assert_eq!(paths, ["openSUSE:Factory:Base", "openSUSE:Tumbleweed"]);
+        assert_eq!(
+            project.repository[1].hostsystem[0].project,
+            "openSUSE:Factory"
+        );
+    }
+
+    #[test]
+    fn maintenance_project() {
+        let project = round_trip("project_maintenance.xml");
+        assert_eq!(project.kind, Some(ProjectKind::Maintenance));
+        let maintains: Vec<String> = project
+            .maintenance
+            .unwrap()
+            .maintains
+            .into_iter()
+            .map(|m| m.project)
+            .collect();
+        assert_eq!(
+            maintains,
+            [
+                "openSUSE:Leap:15.6:Update",
+                "openSUSE:Backports:SLE-15-SP6:Update"
+            ]
+        );
+        assert!(project.source_access.unwrap().disable.is_some());
+        assert!(project.access.unwrap().disable.is_some());
+        assert_eq!(
+            project.repository[0].releasetarget[0].trigger,
+            Some(ReleaseTrigger::Maintenance)
+        );
+    }
+
+    #[test]
+    fn download_on_demand_project() {
+        let project = round_trip("project_dod.xml");
+        assert!(project
+            .description
+            .starts_with("Binaries downloaded on demand"));
+        assert_eq!(
+            project.remote_url.as_deref(),
+            Some("https://api.opensuse.org/public")
+        );
+        let download = &project.repository[0].download;
+        assert_eq!(download.len(), 2);
+        assert!(matches!(download[0].repotype, RepoType::Deb));
+        assert_eq!(download[0].archfilter.as_deref(), Some("x86_64,noarch"));
+        let master = download[0].master.as_ref().unwrap();
+        assert_eq!(master.sslfingerprint.as_deref(), Some("sha256:00:11:22:33"));
+        assert!(download[0]
+            .pubkey
+            .as_ref()
+            .unwrap()
+            .starts_with("-----BEGIN PGP PUBLIC KEY BLOCK-----\nmQINBG"));
+        assert!(download[1].master.is_none());
+    }
+}
@@ -1,8 +1,9 @@
-use yaserde::YaDeserialize;
+use yaserde::{YaDeserialize, YaSerialize};
 
 use super::obs::BuildArch;
 
-#[derive(Debug, Clone, YaDeserialize)]
+#[derive(Debug, Clone, Default, YaDeserialize, YaSerialize)]
+#[yaserde(rename = "repository")]
 pub struct Repository {
     #[yaserde(attribute)]
     pub name: String,
@@ -19,7 +20,7 @@ pub struct Repository {
     pub arch: Vec<BuildArch>,
 }
 
-#[derive(Debug, Clone, YaDeserialize)]
+#[derive(Debug, Clone, YaDeserialize, YaSerialize)]
 pub enum RebuildMode {
     #[yaserde(rename = "transitive")]
     Transitive,
@@ -35,7 +36,7 @@ impl Default for RebuildMode {
     }
 }
 
-#[derive(Debug, Clone, YaDeserialize)]
+#[derive(Debug, Clone, YaDeserialize, YaSerialize)]
 pub enum BlockMode {
     #[yaserde(rename = "all")]
     All,
@@ -51,7 +52,7 @@ impl Default for BlockMode {
     }
 }
 
-#[derive(Debug, Clone, YaDeserialize)]
+#[derive(Debug, Clone, YaDeserialize, YaSerialize)]
 pub enum LinkedBuildMode {
     #[yaserde(rename = "off")]
     Off,
@@ -69,7 +70,7 @@ impl Default for LinkedBuildMode {
     }
 }
 
-#[derive(Debug, Clone, YaDeserialize)]
+#[derive(Debug, Clone, YaDeserialize, YaSerialize)]
 pub struct Path {
     #[yaserde(attribute)]
     pub project: String,
@@ -77,19 +78,20 @@ pub struct Path {
     pub repository: String,
 }
 
-#[derive(Debug, Clone, YaDeserialize)]
+#[derive(Debug, Clone, YaDeserialize, YaSerialize)]
 pub struct ReleaseTarget {
     #[yaserde(attribute)]
     pub project: String,
     #[yaserde(attribute)]
     pub repository: String,
     #[yaserde(attribute)]
-    pub trigger: ReleaseTrigger,
+    pub trigger: Option<ReleaseTrigger>,
 }
 
-#[derive(Debug, Clone, YaDeserialize, PartialEq)]
+#[derive(Debug, Clone, Default, YaDeserialize, YaSerialize, PartialEq)]
 pub enum ReleaseTrigger {
-    None,
+    // yaserde needs a default for enums, OBS releases manually when no trigger is set
+    #[default]
     #[yaserde(rename = "manual")]
     Manual,
     #[yaserde(rename = "maintenance")]
@@ -98,13 +100,7 @@ pub enum ReleaseTrigger {
     Obsgendiff,
 }
 
-impl Default for ReleaseTrigger {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
-#[derive(Debug, Clone, YaDeserialize)]
+#[derive(Debug, Clone, YaDeserialize, YaSerialize)]
 pub struct Download {
     #[yaserde(attribute)]
     pub arch: String,
@@ -117,7 +113,7 @@ pub struct Download {
     pub pubkey: Option<String>,
 }
 
-#[derive(Debug, Clone, YaDeserialize)]
+#[derive(Debug, Clone, YaDeserialize, YaSerialize)]
 pub enum RepoType {
     #[yaserde(rename = "rpmmd")]
     Rpmmd,
@@ -140,8 +136,10 @@ impl Default for RepoType {
     }
 }
 
-#[derive(Debug, Clone, YaDeserialize)]
+#[derive(Debug, Clone, YaDeserialize, YaSerialize)]
 pub struct Master {
+    #[yaserde(attribute)]
     pub url: String,
+    #[yaserde(attribute)]
     pub sslfingerprint: Option<String>,
 }
@@ -1,5 +1,7 @@
 use yaserde::{YaDeserialize, YaSerialize};
 
+use super::obs::LocalRole;
+
 #[derive(Debug, YaSerialize, YaDeserialize)]
 #[yaserde(rename = "request")]
 pub struct Request {
@@ -7,27 +9,104 @@ pub struct Request {
     pub description: String,
     #[yaserde(attribute)]
     pub id: Option<u32>,
+    #[yaserde(attribute)]
+    pub creator: Option<String>,
+    pub state: Option<State>,
+    pub review: Vec<Review>,
+    pub history: Vec<History>,
 }
 
-#[derive(Debug, YaSerialize, YaDeserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, YaSerialize, YaDeserialize)]
 pub struct Action {
     #[yaserde(attribute, rename = "type")]
     pub _type: String,
     pub source: Option<Target>,
-    pub target: Target,
+    pub target: Option<Target>,
+    pub person: Option<RoleAssignee>,
+    pub group: Option<RoleAssignee>,
+    pub options: Option<Options>,
+    /// Requests bundled by a `group` action
+    pub grouped: Vec<Grouped>,
 }
 
-#[derive(Debug, YaSerialize, YaDeserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, YaSerialize, YaDeserialize)]
 pub struct Target {
     #[yaserde(attribute)]
     pub project: String,
     #[yaserde(attribute)]
-    pub package: String,
+    pub package: Option<String>,
+    #[yaserde(attribute)]
+    pub rev: Option<String>,
+    #[yaserde(attribute)]
+    pub releaseproject: Option<String>,
+    #[yaserde(attribute)]
+    pub repository: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, YaSerialize, YaDeserialize)]
+pub struct RoleAssignee {
+    #[yaserde(attribute)]
+    pub name: String,
     #[yaserde(attribute)]
-    pub rev: Option<u32>,
+    pub role: Option<LocalRole>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, YaSerialize, YaDeserialize)]
+pub struct Options {
+    pub sourceupdate: Option<String>,
+    pub updatelink: Option<bool>,
+    pub makeoriginolder: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, YaSerialize, YaDeserialize)]
+pub struct Grouped {
+    #[yaserde(attribute)]
+    pub id: u32,
 }
 
 #[derive(Debug, YaDeserialize)]
 pub struct Collection {
     pub request: Vec<Request>,
 }
+
+#[derive(Debug, YaSerialize, YaDeserialize)]
+pub struct State {
+    #[yaserde(attribute)]
+    pub name: String,
+    #[yaserde(attribute)]
+    pub who: Option<String>,
+    #[yaserde(attribute)]
+    pub when: Option<String>,
+    #[yaserde(attribute)]
+    pub superseded_by: Option<u32>,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, YaSerialize, YaDeserialize)]
+pub struct Review {
+    #[yaserde(attribute)]
+    pub state: String,
+    #[yaserde(attribute)]
+    pub by_user: Option<String>,
+    #[yaserde(attribute)]
+    pub by_group: Option<String>,
+    #[yaserde(attribute)]
+    pub by_project: Option<String>,
+    #[yaserde(attribute)]
+    pub by_package: Option<String>,
+    #[yaserde(attribute)]
+    pub who: Option<String>,
+    #[yaserde(attribute)]
+    pub when: Option<String>,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, YaSerialize, YaDeserialize)]
+pub struct History {
+    #[yaserde(attribute)]
+    pub who: String,
+    #[yaserde(attribute)]
+    pub when: String,
+    pub description: Option<String>,
+    pub comment: Option<String>,
+}
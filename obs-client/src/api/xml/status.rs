@@ -0,0 +1,28 @@
+use yaserde::YaDeserialize;
+
+#[derive(Debug, Clone, YaDeserialize)]
+#[yaserde(rename = "status")]
+pub struct Status {
+    #[yaserde(attribute)]
+    pub code: String,
+    pub summary: Option<String>,
+    pub details: Option<String>,
+    pub data: Vec<StatusData>,
+}
+
+impl Status {
+    pub fn data(&self, name: &str) -> Option<&str> {
+        self.data
+            .iter()
+            .find(|d| d.name == name)
+            .map(|d| d.value.as_str())
+    }
+}
+
+#[derive(Debug, Clone, YaDeserialize)]
+pub struct StatusData {
+    #[yaserde(attribute)]
+    pub name: String,
+    #[yaserde(text)]
+    pub value: String,
+}
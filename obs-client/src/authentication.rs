@@ -4,38 +4,48 @@ use reqwest::header::HeaderValue;
 use base64::prelude::BASE64_STANDARD;
 use base64::write::EncoderWriter;
 use ssh_encoding::Encode;
-use ssh_key::HashAlg;
+use ssh_key::{Fingerprint, HashAlg, SshSig};
 use std::env;
 use std::fmt::Debug;
-use std::io::Write;
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
 use std::process::Command;
+use std::str::FromStr;
 use std::time::SystemTime;
+use tokio::sync::OnceCell;
+
+use crate::{error::AuthError, ssh_agent::SshAgent};
 
 #[async_trait::async_trait]
 pub trait AuthMethod: Debug + Sync + Send {
     fn method_name(&self) -> &str;
     fn username(&self) -> &str;
-    async fn authenticate(&self, realm: &str) -> HeaderValue;
+    async fn authenticate(&self, realm: &str) -> Result<HeaderValue, AuthError>;
+
+    /// Send the credentials along the first request instead of waiting for a challenge
+    fn preemptive(&self) -> bool {
+        false
+    }
 }
 
 #[async_trait::async_trait]
 pub trait IntoPassword: Sync + Send {
-    async fn pass(&self) -> String;
+    async fn pass(&self) -> Result<String, AuthError>;
 }
 
 #[async_trait::async_trait]
 impl IntoPassword for String {
-    async fn pass(&self) -> String {
-        self.to_owned()
+    async fn pass(&self) -> Result<String, AuthError> {
+        Ok(self.to_owned())
     }
 }
 
 #[async_trait::async_trait]
 impl<F> IntoPassword for F
 where
-    F: Fn() -> String + Send + Sync,
+    F: Fn() -> Result<String, AuthError> + Send + Sync,
 {
-    async fn pass(&self) -> String {
+    async fn pass(&self) -> Result<String, AuthError> {
         self()
     }
 }
@@ -60,13 +70,53 @@ impl AuthMethod for BasicAuth {
         "Basic"
     }
 
-    async fn authenticate(&self, _realm: &str) -> HeaderValue {
-        basic_auth(&self.username, Some(self.password.pass().await))
+    async fn authenticate(&self, _realm: &str) -> Result<HeaderValue, AuthError> {
+        Ok(basic_auth(
+            &self.username,
+            Some(self.password.pass().await?),
+        ))
+    }
+
+    fn username(&self) -> &str {
+        &self.username
+    }
+}
+
+/// OBS API token, sent as `Authorization: Token <secret>`
+pub struct TokenAuth {
+    pub username: String,
+    pub token: String,
+}
+
+impl Debug for TokenAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenAuth")
+            .field("username", &self.username)
+            .field("token", &"****")
+            .finish()
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthMethod for TokenAuth {
+    fn method_name(&self) -> &str {
+        "Token"
     }
 
     fn username(&self) -> &str {
         &self.username
     }
+
+    async fn authenticate(&self, _realm: &str) -> Result<HeaderValue, AuthError> {
+        let mut header = HeaderValue::from_str(&format!("Token {}", self.token))?;
+        header.set_sensitive(true);
+        Ok(header)
+    }
+
+    // OBS does not challenge for tokens, it has to be sent upfront
+    fn preemptive(&self) -> bool {
+        true
+    }
 }
 
 /// Taken verbatim from reqwest::util
@@ -90,8 +140,26 @@ where
 
 #[derive(Debug)]
 pub struct SSHAuth {
-    pub ssh_key: ssh_key::PrivateKey,
     pub username: String,
+    key: SigningKey,
+}
+
+#[derive(Debug)]
+enum SigningKey {
+    File {
+        key: Box<ssh_key::PrivateKey>,
+        // Decrypted once per process to avoid asking for the passphrase on every signature
+        decrypted: OnceCell<Box<ssh_key::PrivateKey>>,
+        // Agent that may hold the encrypted key, used instead of asking for the passphrase
+        agent: Option<SshAgent>,
+    },
+    Agent {
+        // Missing when there is no agent to talk to, reported when signing
+        agent: Option<SshAgent>,
+        fingerprint: Option<Fingerprint>,
+        // Looked up in the agent on the first signature
+        public_key: OnceCell<Box<ssh_key::PublicKey>>,
+    },
 }
 
 #[async_trait::async_trait]
@@ -104,64 +172,256 @@ impl AuthMethod for SSHAuth {
         &self.username
     }
 
-    async fn authenticate(&self, realm: &str) -> HeaderValue {
+    async fn authenticate(&self, realm: &str) -> Result<HeaderValue, AuthError> {
         let now = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .expect("Before epoch")
             .as_secs();
-        let sig = self.get_signature(realm, &format!("(created): {}", now));
+        let sig = self
+            .get_signature(realm, &format!("(created): {}", now))
+            .await?;
         let buf = format!(
             r#"Signature keyId="{}",algorithm="ssh",headers="(created)",created={},signature="{}""#,
             self.username, now, sig,
         )
         .into_bytes();
-        let mut header = HeaderValue::from_bytes(&buf).expect("should always be valid HeaderValue");
+        let mut header = HeaderValue::from_bytes(&buf)?;
         header.set_sensitive(true);
-        header
+        Ok(header)
     }
 }
 
 impl SSHAuth {
-    fn get_signature(&self, realm: &str, data: &str) -> String {
-        let key = match self.ssh_key.is_encrypted() {
-            true => {
-                let pass = askpass();
-                self.ssh_key.decrypt(pass).unwrap()
+    async fn get_signature(&self, realm: &str, data: &str) -> Result<String, AuthError> {
+        let sig = match &self.key {
+            SigningKey::File {
+                key,
+                decrypted,
+                agent,
+            } => {
+                let agent = match agent {
+                    Some(agent) => agent
+                        .identities()
+                        .await
+                        .is_ok_and(|keys| {
+                            keys.iter()
+                                .any(|k| k.key_data() == key.public_key().key_data())
+                        })
+                        .then_some(agent),
+                    None => None,
+                };
+                match agent {
+                    Some(agent) => agent_signature(agent, key.public_key(), realm, data).await?,
+                    None => {
+                        let key = match key.is_encrypted() {
+                            true => {
+                                decrypted
+                                    .get_or_try_init(|| async {
+                                        Ok::<_, AuthError>(Box::new(key.decrypt(askpass()?)?))
+                                    })
+                                    .await?
+                            }
+                            false => key,
+                        };
+                        key.sign(realm, HashAlg::default(), data.as_bytes())?
+                    }
+                }
+            }
+            SigningKey::Agent {
+                agent,
+                fingerprint,
+                public_key,
+            } => {
+                let agent = agent
+                    .as_ref()
+                    .ok_or(ssh_key::Error::Io(ErrorKind::NotFound))?;
+                let public_key = public_key
+                    .get_or_try_init(|| async {
+                        agent
+                            .identities()
+                            .await?
+                            .into_iter()
+                            .find(|k| match fingerprint {
+                                Some(f) => k.fingerprint(f.algorithm()) == *f,
+                                None => true,
+                            })
+                            .map(Box::new)
+                            .ok_or(ssh_key::Error::Io(ErrorKind::NotFound))
+                    })
+                    .await?;
+                agent_signature(agent, public_key, realm, data).await?
             }
-            false => self.ssh_key.clone(),
         };
-        let sig = key
-            .sign(realm, HashAlg::default(), data.as_bytes())
-            .unwrap();
         let mut writer = Vec::new();
-        sig.encode(&mut writer).unwrap();
-        base64::engine::general_purpose::STANDARD.encode(writer)
+        sig.encode(&mut writer).map_err(ssh_key::Error::from)?;
+        Ok(base64::engine::general_purpose::STANDARD.encode(writer))
     }
 
+    /// Load the private key from `path`, relative paths being looked up in `~/.ssh`.
+    ///
+    /// Encrypted keys are used through the SSH agent when it holds them, so no
+    /// passphrase needs to be asked. A public key (`.pub`) selects the key of the
+    /// agent with the same fingerprint. The agent listens on `SSH_AUTH_SOCK`, unless
+    /// given with [`SSHAuth::with_agent_socket`].
     pub fn new(username: &str, path: &str) -> Result<Self, ssh_key::Error> {
         let path = match path.starts_with(['/', '~']) {
             true => path,
             false => &format!("~/.ssh/{}", path),
         };
-        let path = expanduser::expanduser(path).unwrap();
+        let path = expanduser::expanduser(path)?;
+        if path.extension().is_some_and(|e| e == "pub") {
+            let public_key = ssh_key::PublicKey::read_openssh_file(&path)?;
+            return Ok(SSHAuth {
+                username: username.to_string(),
+                key: SigningKey::Agent {
+                    agent: SshAgent::from_env(),
+                    fingerprint: Some(public_key.fingerprint(HashAlg::default())),
+                    public_key: OnceCell::new(),
+                },
+            });
+        }
         let key = ssh_key::PrivateKey::read_openssh_file(&path)?;
         Ok(SSHAuth {
-            ssh_key: key,
             username: username.to_string(),
+            key: SigningKey::File {
+                agent: key.is_encrypted().then(SshAgent::from_env).flatten(),
+                key: Box::new(key),
+                decrypted: OnceCell::new(),
+            },
+        })
+    }
+
+    /// Sign through the agent listening on `SSH_AUTH_SOCK`.
+    ///
+    /// The key is selected by its fingerprint (e.g. `SHA256:...`), or the first
+    /// key held by the agent is used if none is given. The agent is only queried
+    /// when signing.
+    pub fn from_agent(username: &str, fingerprint: Option<&str>) -> Result<Self, ssh_key::Error> {
+        Ok(SSHAuth {
+            username: username.to_string(),
+            key: SigningKey::Agent {
+                agent: Some(SshAgent::from_env().ok_or(ssh_key::Error::Io(ErrorKind::NotFound))?),
+                fingerprint: fingerprint.map(Fingerprint::from_str).transpose()?,
+                public_key: OnceCell::new(),
+            },
         })
     }
+
+    /// Talk to the agent listening on `socket` instead of `SSH_AUTH_SOCK`
+    pub fn with_agent_socket(mut self, socket: impl Into<PathBuf>) -> Self {
+        let socket_agent = SshAgent::new(socket.into());
+        match &mut self.key {
+            SigningKey::File { key, agent, .. } => {
+                if key.is_encrypted() {
+                    *agent = Some(socket_agent);
+                }
+            }
+            SigningKey::Agent { agent, .. } => *agent = Some(socket_agent),
+        }
+        self
+    }
 }
 
-pub(crate) fn askpass() -> String {
+async fn agent_signature(
+    agent: &SshAgent,
+    public_key: &ssh_key::PublicKey,
+    realm: &str,
+    data: &str,
+) -> Result<SshSig, ssh_key::Error> {
+    let signed_data = SshSig::signed_data(realm, HashAlg::default(), data.as_bytes())?;
+    let signature = agent.sign(public_key, &signed_data).await?;
+    SshSig::new(
+        public_key.key_data().clone(),
+        realm,
+        HashAlg::default(),
+        signature,
+    )
+}
+
+pub(crate) fn askpass() -> Result<String, AuthError> {
     match env::var_os("SSH_ASKPASS") {
         Some(p) => {
-            let mut pass = String::from_utf8(Command::new(p).output().unwrap().stdout).unwrap();
-            pass.pop();
-            pass
+            let output = Command::new(p).output()?;
+            if !output.status.success() {
+                return Err(std::io::Error::other(format!(
+                    "SSH_ASKPASS exited with {}",
+                    output.status
+                ))
+                .into());
+            }
+            let mut pass = String::from_utf8(output.stdout)?;
+            if pass.ends_with('\n') {
+                pass.pop();
+            }
+            Ok(pass)
         }
-        None => dialoguer::Password::new()
+        None => Ok(dialoguer::Password::new()
             .with_prompt("Enter password:")
-            .interact()
-            .unwrap(),
+            .interact()?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ssh_encoding::Decode;
+
+    use super::*;
+    use crate::test_utils::{fixture_key, MockAgent};
+
+    fn fixture_path(name: &str) -> String {
+        format!("{}/tests/fixtures/ssh/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn verify(auth: &SSHAuth, public_key: &ssh_key::PublicKey, signature: &str) {
+        let signature = BASE64_STANDARD.decode(signature).unwrap();
+        let signature = SshSig::decode(&mut signature.as_slice()).unwrap();
+        public_key
+            .verify("realm", b"(created): 1", &signature)
+            .unwrap();
+        assert_eq!(auth.username, "user");
+    }
+
+    #[tokio::test]
+    async fn public_key_file_selects_agent_key() {
+        let mock = MockAgent::start(vec![
+            fixture_key("id_ed25519_encrypted"),
+            fixture_key("id_ed25519"),
+        ]);
+        let auth = SSHAuth::new("user", &fixture_path("id_ed25519.pub"))
+            .unwrap()
+            .with_agent_socket(mock.socket());
+        let signature = auth.get_signature("realm", "(created): 1").await.unwrap();
+        verify(&auth, fixture_key("id_ed25519").public_key(), &signature);
+    }
+
+    #[tokio::test]
+    async fn encrypted_key_is_used_through_agent() {
+        let mock = MockAgent::start(vec![fixture_key("id_ed25519_encrypted")]);
+        let auth = SSHAuth::new("user", &fixture_path("id_ed25519_encrypted"))
+            .unwrap()
+            .with_agent_socket(mock.socket());
+        let signature = auth.get_signature("realm", "(created): 1").await.unwrap();
+        verify(
+            &auth,
+            fixture_key("id_ed25519_encrypted").public_key(),
+            &signature,
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_agent_key_is_reported() {
+        let mock = MockAgent::start(vec![fixture_key("id_ed25519")]);
+        let fingerprint = fixture_key("id_ed25519_encrypted")
+            .public_key()
+            .fingerprint(HashAlg::default());
+        let auth = SSHAuth {
+            username: "user".to_string(),
+            key: SigningKey::Agent {
+                agent: Some(mock.agent()),
+                fingerprint: Some(fingerprint),
+                public_key: OnceCell::new(),
+            },
+        };
+        assert!(auth.get_signature("realm", "(created): 1").await.is_err());
     }
 }
@@ -1,21 +1,39 @@
-use std::{fmt::Debug, hash::Hash, sync::Arc};
+use std::{
+    fmt::Debug,
+    hash::Hash,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use reqwest::{
-    header::{AUTHORIZATION, WWW_AUTHENTICATE},
+    header::{HeaderMap, AUTHORIZATION, WWW_AUTHENTICATE},
     RequestBuilder, Response, StatusCode,
 };
 use reqwest_cookie_store::CookieStoreRwLock;
-use tokio::sync::OnceCell;
+use tokio::sync::Mutex;
 use url::Url;
 
-use crate::{authentication, error::APIError};
+use crate::{
+    api::{
+        configuration::Configuration,
+        xml::{configuration::Configuration as XMLConfiguration, status::Status},
+    },
+    authentication::{self, AuthMethod, BasicAuth, SSHAuth, TokenAuth},
+    cookies::PersistentCookieJar,
+    error::{APIError, ClientError},
+    files::{HostOptions, Oscrc},
+    retry::{self, RetryPolicy},
+    tls,
+};
 
 #[derive(Clone)]
 pub struct OBSClient {
     http_client: reqwest::Client,
     api_url: Url,
     authenticator: Arc<dyn authentication::AuthMethod>,
-    configuration: Arc<OnceCell<Configuration>>,
+    configuration: Arc<Mutex<Option<Arc<Configuration>>>>,
+    retry_policy: RetryPolicy,
+    cookie_jar: Option<Arc<PersistentCookieJar>>,
 }
 
 impl PartialEq for OBSClient {
@@ -38,21 +56,31 @@ impl Debug for OBSClient {
             .field("api_url", &self.api_url.as_str())
             .field("authenticator", &self.authenticator)
             .field("configuration", &self.configuration)
+            .field("retry_policy", &self.retry_policy)
             .finish_non_exhaustive()
     }
 }
-#[derive(Debug, yaserde::YaDeserialize)]
-struct Configuration {
-    obs_url: String,
-}
-
 impl OBSClient {
+    pub fn builder() -> OBSClientBuilder {
+        OBSClientBuilder::default()
+    }
+
+    /// Create a client for the given host (or the default one) as configured in `oscrc`
+    pub fn from_oscrc(
+        cfg: &Oscrc,
+        alias_or_url: Option<&str>,
+        overrides: OscrcOverrides,
+    ) -> Result<Self, ClientError> {
+        OBSClientBuilder::from_oscrc(cfg, alias_or_url, overrides)?.build()
+    }
+
+    /// Create a client keeping its cookies in `cookie_jar`, in memory only. Use
+    /// [`OBSClientBuilder::cookie_jar_file`] to load and save them from an osc cookiejar.
     pub fn new(
         api_url: Url,
         authenticator: Arc<dyn authentication::AuthMethod>,
         cookie_jar: Option<Arc<CookieStoreRwLock>>,
     ) -> Result<Self, APIError> {
-        //TODO: Add cookie Jar
         let mut http_client_builder = reqwest::Client::builder();
         if let Some(cookie_store) = cookie_jar {
             http_client_builder = http_client_builder.cookie_provider(cookie_store);
@@ -63,9 +91,25 @@ impl OBSClient {
             api_url,
             authenticator,
             configuration: Default::default(),
+            retry_policy: Default::default(),
+            cookie_jar: None,
         })
     }
 
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Write the session cookies back to the cookiejar file, if the client was
+    /// created with one. This also happens when the last clone of the client is dropped.
+    pub fn save_cookies(&self) -> Result<(), APIError> {
+        if let Some(cookie_jar) = &self.cookie_jar {
+            cookie_jar.save().map_err(APIError::CookieSaveError)?;
+        }
+        Ok(())
+    }
+
     pub fn user(&self) -> &str {
         self.authenticator.username()
     }
@@ -82,74 +126,466 @@ impl OBSClient {
         self.http_client.post(url)
     }
 
+    pub(crate) fn put(&self, route: &[&str]) -> RequestBuilder {
+        let mut url = self.api_url.clone();
+        url.path_segments_mut().expect("Base url").extend(route);
+        self.http_client.put(url)
+    }
+
     pub(crate) fn delete(&self, route: &[&str]) -> RequestBuilder {
         let mut url = self.api_url.clone();
         url.path_segments_mut().expect("Base url").extend(route);
         self.http_client.delete(url)
     }
 
-    pub(crate) async fn execute(
+    /// Execute the request, retrying transient failures for idempotent verbs only
+    pub(crate) async fn execute(&self, request: reqwest::Request) -> Result<Response, APIError> {
+        let retryable = retry::is_idempotent(request.method());
+        self.execute_with_retries(request, retryable).await
+    }
+
+    /// Execute the request, retrying transient failures if `retryable` is set
+    async fn execute_with_retries(
         &self,
-        request: reqwest::Request,
-    ) -> Result<Response, reqwest::Error> {
+        mut request: reqwest::Request,
+        retryable: bool,
+    ) -> Result<Response, APIError> {
+        let mut attempt: u32 = 0;
+        loop {
+            let next = match retryable && u64::from(attempt) < self.retry_policy.max_retries {
+                true => request.try_clone(),
+                false => None,
+            };
+            let result = self.execute_authenticated(request).await;
+            let delay = match (&result, next.is_some()) {
+                (Ok(resp), true) if retry::is_transient_status(resp.status()) => {
+                    self.retry_policy.delay(attempt, retry::retry_after(resp))
+                }
+                (Err(APIError::HTTPError(e)), true) if retry::is_transient_error(e) => {
+                    self.retry_policy.delay(attempt, None)
+                }
+                _ => return check_status(result?).await,
+            };
+            tracing::warn!(
+                attempt = attempt + 1,
+                max_retries = self.retry_policy.max_retries,
+                ?delay,
+                "Transient error from OBS, retrying"
+            );
+            tokio::time::sleep(delay).await;
+            request = next.expect("Checked above");
+            attempt += 1;
+        }
+    }
+
+    async fn execute_authenticated(
+        &self,
+        mut request: reqwest::Request,
+    ) -> Result<Response, APIError> {
+        if self.authenticator.preemptive() {
+            request
+                .headers_mut()
+                .insert(AUTHORIZATION, self.authenticator.authenticate("").await?);
+        }
         let req_bak = request.try_clone();
         let resp = self.http_client.execute(request).await?;
-        if resp.status().is_success() {
+        if resp.status() != StatusCode::UNAUTHORIZED {
             return Ok(resp);
         }
 
-        if resp.status() == StatusCode::UNAUTHORIZED {
-            if let Some(mut req_bak) = req_bak {
-                let auth = resp.headers().get_all(WWW_AUTHENTICATE);
-                for auth_method in auth {
-                    if let Some(auth_params) = auth_method
-                        .to_str()
-                        .unwrap_or_default()
-                        .strip_prefix(self.authenticator.method_name())
+        if let Some(mut req_bak) = req_bak {
+            let auth = resp.headers().get_all(WWW_AUTHENTICATE);
+            for auth_method in auth {
+                if let Some(auth_params) = auth_method
+                    .to_str()
+                    .unwrap_or_default()
+                    .strip_prefix(self.authenticator.method_name())
+                {
+                    if let Some(realm) = auth_params
+                        .trim()
+                        .split(',')
+                        .filter(|s| !s.is_empty())
+                        .find_map(|s| Some(s.strip_prefix("realm=")?.trim_matches('"')))
                     {
-                        if let Some(realm) = auth_params
-                            .trim()
-                            .split(',')
-                            .filter(|s| !s.is_empty())
-                            .find_map(|s| Some(s.strip_prefix("realm=")?.trim_matches('"')))
-                        {
-                            req_bak.headers_mut().insert(
-                                AUTHORIZATION,
-                                self.authenticator.authenticate(realm).await,
-                            );
-                            return self.http_client.execute(req_bak).await?.error_for_status();
-                        }
+                        req_bak
+                            .headers_mut()
+                            .insert(AUTHORIZATION, self.authenticator.authenticate(realm).await?);
+                        return Ok(self.http_client.execute(req_bak).await?);
                     }
                 }
             }
         }
 
-        resp.error_for_status()
+        Ok(resp)
+    }
+
+    async fn fetch_configuration(&self) -> Result<Configuration, APIError> {
+        let req = self
+            .get(&["configuration"])
+            .header("Accept", "application/xml; charset=utf-8")
+            .build()?;
+        let resp = self.execute(req).await?;
+        let configuration: XMLConfiguration =
+            yaserde::de::from_str(&resp.text().await?).map_err(APIError::XMLParseError)?;
+        configuration.try_into()
+    }
+
+    /// Get the OBS instance configuration, fetching it on first use
+    pub async fn configuration(&self) -> Result<Arc<Configuration>, APIError> {
+        let mut guard = self.configuration.lock().await;
+        if let Some(configuration) = guard.as_ref() {
+            return Ok(configuration.clone());
+        }
+        let configuration = Arc::new(self.fetch_configuration().await?);
+        *guard = Some(configuration.clone());
+        Ok(configuration)
+    }
+
+    /// Drop the cached configuration, the next call will fetch it again
+    pub async fn invalidate_configuration(&self) {
+        self.configuration.lock().await.take();
+    }
+
+    pub async fn get_obs_route(&self, route: &[&str]) -> Result<Url, APIError> {
+        let mut url = self
+            .configuration()
+            .await?
+            .obs_url
+            .clone()
+            .ok_or(APIError::MissingConfiguration("obs_url"))?;
+        url.path_segments_mut()
+            .map_err(|_| APIError::InvalidObject)?
+            .pop_if_empty()
+            .extend(route);
+        Ok(url)
+    }
+}
+
+/// Values taking precedence over what is found in `oscrc`, usually coming from the command line
+#[derive(Debug, Clone, Default)]
+pub struct OscrcOverrides {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub sshkey: Option<String>,
+    /// Store the password asked for in the credentials manager, see
+    /// [`Oscrc::get_password_provider`]
+    pub store_password: bool,
+}
+
+#[derive(Default)]
+pub struct OBSClientBuilder {
+    api_url: Option<Url>,
+    authenticator: Option<Arc<dyn AuthMethod>>,
+    cookie_jar: Option<Arc<CookieStoreRwLock>>,
+    cookie_jar_file: Option<PathBuf>,
+    retry_policy: RetryPolicy,
+    http_headers: HeaderMap,
+    cafile: Option<PathBuf>,
+    capath: Option<PathBuf>,
+    http_debug: bool,
+}
+
+impl OBSClientBuilder {
+    /// Resolve the host from its alias or url and pick the authentication method:
+    /// an explicit password wins, then the host API token, then the host or global
+    /// SSH key, then the configured credentials manager.
+    pub fn from_oscrc(
+        cfg: &Oscrc,
+        alias_or_url: Option<&str>,
+        overrides: OscrcOverrides,
+    ) -> Result<Self, ClientError> {
+        let api_url = match alias_or_url {
+            Some(alias) => cfg
+                .apiurl_from_alias(alias)
+                .ok_or_else(|| ClientError::UnknownHost(alias.to_string()))?,
+            None => cfg.apiurl.clone(),
+        };
+        let host_options = cfg
+            .hosts_options
+            .get(&api_url)
+            .ok_or_else(|| ClientError::UnknownHost(api_url.to_string()))?;
+        let username = overrides
+            .username
+            .unwrap_or_else(|| host_options.username.clone());
+        let sshkey = overrides
+            .sshkey
+            .or_else(|| host_options.sshkey.clone())
+            .or_else(|| cfg.sshkey.clone());
+        let token = host_options.token.clone();
+        let authenticator: Arc<dyn AuthMethod> = match (overrides.password, token, sshkey) {
+            (Some(password), _, _) => Arc::new(BasicAuth {
+                username,
+                password: Box::new(password),
+            }),
+            (None, Some(token), _) => Arc::new(TokenAuth { username, token }),
+            (None, None, Some(key)) => Arc::new(SSHAuth::new(&username, &key)?),
+            (None, None, None) => Arc::new(BasicAuth {
+                username,
+                password: cfg
+                    .get_password_provider(&api_url, overrides.store_password)
+                    .ok_or_else(|| ClientError::UnknownHost(api_url.to_string()))?,
+            }),
+        };
+
+        Ok(Self::default()
+            .api_url(api_url)
+            .authenticator(authenticator)
+            .cookie_jar_file(&cfg.cookiejar)
+            .retry_policy(RetryPolicy::from(cfg))
+            .http_debug(cfg.http_debug)
+            .host_options(host_options))
+    }
+
+    /// Apply the TLS settings and custom headers of an `oscrc` host section
+    pub fn host_options(mut self, host_options: &HostOptions) -> Self {
+        self.http_headers = host_options.http_headers.clone();
+        self.cafile = host_options.cafile.clone();
+        self.capath = host_options.capath.clone();
+        self
+    }
+
+    pub fn api_url(mut self, api_url: Url) -> Self {
+        self.api_url = Some(api_url);
+        self
+    }
+
+    pub fn authenticator(mut self, authenticator: Arc<dyn AuthMethod>) -> Self {
+        self.authenticator = Some(authenticator);
+        self
+    }
+
+    pub fn cookie_jar(mut self, cookie_jar: Arc<CookieStoreRwLock>) -> Self {
+        self.cookie_jar = Some(cookie_jar);
+        self
+    }
+
+    /// Load the cookies from an osc LWP cookiejar file and save them back into it
+    pub fn cookie_jar_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.cookie_jar_file = Some(path.as_ref().to_path_buf());
+        self
     }
 
-    async fn init_configuration(&self) -> Configuration {
-        let resp = self
-            .execute(
-                self.get(&["configuration"])
-                    .header("Accept", "application/xml; charset=utf-8")
-                    .build()
-                    .unwrap(),
-            )
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Headers sent along every request
+    pub fn http_headers(mut self, http_headers: HeaderMap) -> Self {
+        self.http_headers = http_headers;
+        self
+    }
+
+    /// PEM bundle or DER file containing additional trusted root certificates
+    pub fn ca_file(mut self, cafile: impl AsRef<Path>) -> Self {
+        self.cafile = Some(cafile.as_ref().to_path_buf());
+        self
+    }
+
+    /// OpenSSL style hashed directory containing additional trusted root certificates
+    pub fn ca_path(mut self, capath: impl AsRef<Path>) -> Self {
+        self.capath = Some(capath.as_ref().to_path_buf());
+        self
+    }
+
+    /// Log the raw HTTP traffic, at the `trace` level of `reqwest::connect::verbose`
+    pub fn http_debug(mut self, http_debug: bool) -> Self {
+        self.http_debug = http_debug;
+        self
+    }
+
+    pub fn build(self) -> Result<OBSClient, ClientError> {
+        let api_url = self.api_url.ok_or(ClientError::MissingOption("api_url"))?;
+        let authenticator = self
+            .authenticator
+            .ok_or(ClientError::MissingOption("authenticator"))?;
+        let mut http_client_builder = reqwest::Client::builder()
+            .default_headers(self.http_headers)
+            .connection_verbose(self.http_debug);
+        let persistent_jar = match &self.cookie_jar_file {
+            Some(path) => Some(Arc::new(PersistentCookieJar::load(path)?)),
+            None => None,
+        };
+        if let Some(cookie_jar) = &persistent_jar {
+            http_client_builder = http_client_builder.cookie_provider(cookie_jar.store.clone());
+        } else if let Some(cookie_store) = self.cookie_jar {
+            http_client_builder = http_client_builder.cookie_provider(cookie_store);
+        }
+        let mut certificates = Vec::new();
+        if let Some(cafile) = &self.cafile {
+            certificates.extend(tls::load_certificates_file(cafile)?);
+        }
+        if let Some(capath) = &self.capath {
+            certificates.extend(tls::load_certificates_dir(capath)?);
+        }
+        for certificate in certificates {
+            http_client_builder = http_client_builder.add_root_certificate(certificate);
+        }
+        Ok(OBSClient {
+            http_client: http_client_builder.build()?,
+            api_url,
+            authenticator,
+            configuration: Default::default(),
+            retry_policy: self.retry_policy,
+            cookie_jar: persistent_jar,
+        })
+    }
+}
+
+/// Turn error responses into [`APIError::OBSStatus`] when OBS sent a status document
+async fn check_status(resp: Response) -> Result<Response, APIError> {
+    let http_status = resp.status();
+    let Err(error) = resp.error_for_status_ref() else {
+        return Ok(resp);
+    };
+    let body = resp.text().await.unwrap_or_default();
+    match yaserde::de::from_str::<Status>(&body) {
+        Ok(status) if !status.code.is_empty() => Err(APIError::OBSStatus {
+            http_status,
+            code: status.code,
+            summary: status.summary,
+            details: status.details,
+        }),
+        _ => Err(APIError::HTTPError(error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{api_fixture, MockResponse, MockServer};
+
+    async fn get(status: u16, body: String) -> Result<Response, APIError> {
+        let server = MockServer::start(move |_| MockResponse::new(status).body(body.clone())).await;
+        let client = server.client();
+        let req = client.get(&["source", "prj", "pkg"]).build().unwrap();
+        client.execute(req).await
+    }
+
+    #[tokio::test]
+    async fn status_document_becomes_obs_status() {
+        match get(400, api_fixture("status_invalid_flag.xml")).await {
+            Err(APIError::OBSStatus {
+                http_status,
+                code,
+                summary,
+                details,
+            }) => {
+                assert_eq!(http_status, StatusCode::BAD_REQUEST);
+                assert_eq!(code, "validation_failed");
+                assert_eq!(summary.as_deref(), Some("package validation error"));
+                assert_eq!(
+                    details.as_deref(),
+                    Some("5:0: ERROR: Element build has extra content: enabled")
+                );
+            }
+            other => panic!("expected an OBS status, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn not_found_keeps_obs_code() {
+        let error = get(404, api_fixture("status_unknown_package.xml"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.http_status(), Some(StatusCode::NOT_FOUND));
+        assert_eq!(error.obs_code(), Some("unknown_package"));
+        match error {
+            APIError::OBSStatus {
+                summary, details, ..
+            } => {
+                assert_eq!(summary.as_deref(), Some("home:someone/missing"));
+                assert_eq!(details, None);
+            }
+            other => panic!("expected an OBS status, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn unauthorized_keeps_obs_code() {
+        let error = get(401, api_fixture("status_authentication_required.xml"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.http_status(), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(error.obs_code(), Some("authentication_required"));
+    }
+
+    #[tokio::test]
+    async fn other_bodies_stay_http_errors() {
+        let error = get(400, "<html><body>Bad Request</body></html>".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, APIError::HTTPError(_)));
+        assert_eq!(error.http_status(), Some(StatusCode::BAD_REQUEST));
+        assert_eq!(error.obs_code(), None);
+    }
+
+    #[tokio::test]
+    async fn success_is_untouched() {
+        let resp = get(200, api_fixture("status_unknown_package.xml"))
             .await
             .unwrap();
-        yaserde::de::from_str(&resp.text().await.unwrap()).unwrap()
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.text().await.unwrap().contains("unknown_package"));
+    }
+
+    async fn configuration_server(name: &'static str) -> MockServer {
+        MockServer::start(move |_| MockResponse::new(200).body(api_fixture(name))).await
+    }
+
+    #[tokio::test]
+    async fn configuration_is_fetched_once() {
+        let server = configuration_server("configuration.xml").await;
+        let client = server.client();
+        let configuration = client.configuration().await.unwrap();
+        assert_eq!(configuration.title, "Open Build Service");
+        assert!(configuration.download_on_demand);
+        assert!(!configuration.enforce_project_keys);
+        assert!(configuration.allow_user_to_create_home_project);
+        assert_eq!(configuration.http_proxy, None);
+        assert_eq!(configuration.cleanup_after_days, None);
+        assert_eq!(configuration.schedulers, ["aarch64", "i586", "x86_64"]);
+
+        assert_eq!(
+            client
+                .get_obs_route(&["package", "show", "prj", "pkg"])
+                .await
+                .unwrap()
+                .as_str(),
+            "https://build.opensuse.org/package/show/prj/pkg"
+        );
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/configuration");
+    }
+
+    #[tokio::test]
+    async fn invalidated_configuration_is_fetched_again() {
+        let server = configuration_server("configuration.xml").await;
+        let client = server.client();
+        client.configuration().await.unwrap();
+        client.invalidate_configuration().await;
+        client.configuration().await.unwrap();
+        assert_eq!(server.requests().len(), 2);
     }
 
-    pub async fn get_obs_route(&self, route: &[&str]) -> Url {
-        let mut url = Url::parse(
-            &self
-                .configuration
-                .get_or_init(|| async { self.init_configuration().await })
+    #[tokio::test]
+    async fn missing_obs_url_is_an_error() {
+        let server = configuration_server("configuration_no_obs_url.xml").await;
+        let client = server.client();
+        assert!(matches!(
+            client.get_obs_route(&["project", "show", "prj"]).await,
+            Err(APIError::MissingConfiguration("obs_url"))
+        ));
+        assert_eq!(
+            client
+                .configuration()
                 .await
-                .obs_url,
-        )
-        .unwrap();
-        url.path_segments_mut().unwrap().extend(route);
-        url
+                .unwrap()
+                .download_url
+                .as_ref()
+                .map(Url::as_str),
+            Some("https://download.example.com/repositories")
+        );
     }
 }
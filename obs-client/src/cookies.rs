@@ -1,11 +1,14 @@
 use std::{
-    io::{BufRead, BufReader},
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
-use cookie_store::{Cookie, CookieResult, CookieStore};
+use cookie_store::{Cookie, CookieDomain, CookieExpiration, CookieResult, CookieStore};
 use reqwest_cookie_store::CookieStoreRwLock;
-use time::OffsetDateTime;
+use time::{macros::format_description, OffsetDateTime};
 use url::Url;
 
 use crate::files::Oscrc;
@@ -57,6 +60,13 @@ fn parse_cookie(line: &str) -> Result<Cookie<'static>, cookie_store::CookieError
         raw_cookie.set_expires(e);
     }
     let url = get_url_from_cookie(&raw_cookie)?;
+    // Like python's cookiejar, only a leading dot marks a domain cookie, others are host-only
+    let host_only = attributes
+        .iter()
+        .any(|(k, v)| k.eq_ignore_ascii_case("domain") && !v.is_some_and(|d| d.starts_with('.')));
+    if host_only {
+        raw_cookie.unset_domain();
+    }
     let cookie = Cookie::try_from_raw_cookie(&raw_cookie, &url)?;
     Ok(cookie)
 }
@@ -78,7 +88,10 @@ pub(crate) fn parse_lwp_cookiejar(
     reader: impl BufRead,
 ) -> Result<CookieStore, cookie_store::CookieError> {
     let mut lines = reader.lines();
-    let line = lines.next().unwrap().unwrap();
+    let line = match lines.next() {
+        Some(line) => line.map_err(|_| cookie_store::CookieError::Parse)?,
+        None => return Ok(CookieStore::default()),
+    };
     if !line.starts_with("#LWP-Cookies-") {
         return Err(cookie_store::CookieError::Parse);
     }
@@ -86,16 +99,304 @@ pub(crate) fn parse_lwp_cookiejar(
 }
 
 pub fn get_osc_cookiejar(cfg: &Oscrc) -> Result<Arc<CookieStoreRwLock>, cookie_store::CookieError> {
-    if let Ok(cookiejar_file) = std::fs::File::open(&cfg.cookiejar) {
-        let cookie_jar_reader = BufReader::new(cookiejar_file);
-        let cookie_jar = parse_lwp_cookiejar(cookie_jar_reader)?;
-        return Ok(Arc::new(reqwest_cookie_store::CookieStoreRwLock::new(
-            cookie_jar,
-        )));
-    }
-
-    let cookie_jar = CookieStore::new(None);
     Ok(Arc::new(reqwest_cookie_store::CookieStoreRwLock::new(
-        cookie_jar,
+        load_lwp_cookiejar(&cfg.cookiejar)?,
     )))
 }
+
+/// Load a LWP cookiejar, a missing file gives an empty store.
+///
+/// No lock is needed, the file is only ever replaced atomically by [`save_lwp_cookiejar`].
+pub(crate) fn load_lwp_cookiejar(path: &Path) -> Result<CookieStore, cookie_store::CookieError> {
+    let cookiejar_file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => return Ok(CookieStore::default()),
+    };
+    parse_lwp_cookiejar(BufReader::new(&cookiejar_file))
+}
+
+/// Write the store in the `#LWP-Cookies-2.0` format used by osc (python's `LWPCookieJar`)
+pub(crate) fn write_lwp_cookiejar(
+    store: &CookieStore,
+    mut writer: impl Write,
+) -> std::io::Result<()> {
+    writeln!(writer, "#LWP-Cookies-2.0")?;
+    for cookie in store.iter_unexpired() {
+        if let Some(line) = lwp_cookie_line(cookie) {
+            writeln!(writer, "Set-Cookie3: {}", line)?;
+        }
+    }
+    Ok(())
+}
+
+/// Merge the store into the cookiejar file.
+///
+/// The merged jar is written to a temporary file renamed over the jar, so osc never
+/// reads a partial file. Writers serialize on `<jar>.lock` so cookies written
+/// meanwhile by another process are not lost.
+pub(crate) fn save_lwp_cookiejar(store: &CookieStore, path: &Path) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let lock = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .mode(0o600)
+        .open(sibling_path(path, "lock"))?;
+    lock.lock()?;
+    let mut merged = match std::fs::read_to_string(path) {
+        Ok(content) => parse_lwp_cookiejar(content.as_bytes()).unwrap_or_default(),
+        Err(e) if e.kind() == ErrorKind::NotFound => CookieStore::default(),
+        Err(e) => return Err(e),
+    };
+    for cookie in store.iter_unexpired() {
+        if let Some(url) = cookie_url(cookie) {
+            let _ = merged.insert(cookie.clone(), &url);
+        }
+    }
+    let tmp_path = sibling_path(path, &format!("{}.tmp", std::process::id()));
+    let result = (|| {
+        let mut tmp = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&tmp_path)?;
+        write_lwp_cookiejar(&merged, &mut tmp)?;
+        tmp.sync_all()?;
+        std::fs::rename(&tmp_path, path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// `path` with `.<suffix>` appended to its file name
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+fn cookie_url(cookie: &Cookie) -> Option<Url> {
+    let domain = match &cookie.domain {
+        CookieDomain::HostOnly(d) | CookieDomain::Suffix(d) => d,
+        _ => return None,
+    };
+    let scheme = match cookie.secure() {
+        Some(true) => "https",
+        _ => "http",
+    };
+    Url::parse(&format!("{}://{}{}", scheme, domain, &*cookie.path)).ok()
+}
+
+fn lwp_cookie_line(cookie: &Cookie) -> Option<String> {
+    let domain = match &cookie.domain {
+        CookieDomain::HostOnly(d) => d.clone(),
+        CookieDomain::Suffix(d) => format!(".{}", d),
+        _ => return None,
+    };
+    let mut attributes: Vec<(&str, Option<String>)> = vec![
+        (cookie.name(), Some(cookie.value().to_string())),
+        ("path", Some(cookie.path.to_string())),
+        ("domain", Some(domain)),
+    ];
+    if cookie.path.is_from_path_attr() {
+        attributes.push(("path_spec", None));
+    }
+    if matches!(cookie.domain, CookieDomain::Suffix(_)) {
+        attributes.push(("domain_dot", None));
+    }
+    if cookie.secure().unwrap_or(false) {
+        attributes.push(("secure", None));
+    }
+    match cookie.expires {
+        CookieExpiration::AtUtc(expires) => attributes.push((
+            "expires",
+            expires
+                .format(format_description!(
+                    "[year]-[month]-[day] [hour]:[minute]:[second]Z"
+                ))
+                .ok(),
+        )),
+        CookieExpiration::SessionEnd => attributes.push(("discard", None)),
+    }
+    if cookie.http_only().unwrap_or(false) {
+        attributes.push(("HttpOnly", Some("None".to_string())));
+    }
+    attributes.push(("version", Some("0".to_string())));
+    Some(
+        attributes
+            .into_iter()
+            .map(|(k, v)| match v {
+                Some(v) => format!("{}={}", k, quote_value(&v)),
+                None => k.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join("; "),
+    )
+}
+
+/// Quote like python's `http.cookiejar.join_header_words`
+fn quote_value(value: &str) -> String {
+    if !value.is_empty() && value.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return value.to_string();
+    }
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Cookie store saved back to its file when the last client using it is dropped
+#[derive(Debug)]
+pub(crate) struct PersistentCookieJar {
+    pub(crate) store: Arc<CookieStoreRwLock>,
+    path: PathBuf,
+}
+
+impl PersistentCookieJar {
+    pub(crate) fn load(path: &Path) -> Result<Self, cookie_store::CookieError> {
+        Ok(Self {
+            store: Arc::new(CookieStoreRwLock::new(load_lwp_cookiejar(path)?)),
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub(crate) fn save(&self) -> std::io::Result<()> {
+        let store = self
+            .store
+            .read()
+            .map_err(|_| std::io::Error::other("Poisoned cookie store"))?;
+        save_lwp_cookiejar(&store, &self.path)
+    }
+}
+
+impl Drop for PersistentCookieJar {
+    fn drop(&mut self) {
+        if let Err(error) = self.save() {
+            tracing::warn!(%error, path = %self.path.display(), "Unable to save cookiejar");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    /// Written by python's `http.cookiejar.LWPCookieJar`, as used by osc
+    const OSC_COOKIEJAR: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/cookiejar"
+    ));
+
+    fn sorted_lines(content: &str) -> Vec<&str> {
+        let mut lines: Vec<&str> = content.lines().collect();
+        lines.sort();
+        lines
+    }
+
+    fn cookie<'a>(store: &'a CookieStore, domain: &str, name: &str) -> &'a Cookie<'static> {
+        store
+            .iter_any()
+            .find(|c| {
+                c.name() == name
+                    && matches!(&c.domain, CookieDomain::HostOnly(d) | CookieDomain::Suffix(d) if d == domain)
+            })
+            .unwrap_or_else(|| panic!("missing cookie {}", name))
+    }
+
+    #[test]
+    fn parse_osc_cookiejar() {
+        let store = parse_lwp_cookiejar(OSC_COOKIEJAR.as_bytes()).unwrap();
+        assert_eq!(store.iter_any().count(), 3);
+
+        let session = cookie(&store, "opensuse.org", "openSUSE_session");
+        assert_eq!(session.value(), "abc123DEF");
+        assert!(matches!(&session.domain, CookieDomain::Suffix(d) if d == "opensuse.org"));
+        assert_eq!(session.secure(), Some(true));
+        assert_eq!(session.http_only(), Some(true));
+        assert!(matches!(
+            session.expires,
+            CookieExpiration::AtUtc(t) if t.unix_timestamp() == 1924992000
+        ));
+
+        let quoted = cookie(&store, "build.example.com", "_obs_api_session");
+        assert_eq!(quoted.value(), "a/b+c=d");
+        assert!(matches!(&quoted.domain, CookieDomain::HostOnly(_)));
+
+        let session_only = cookie(&store, "build.example.com", "session_only");
+        assert_eq!(&*session_only.path, "/source");
+        assert!(matches!(session_only.expires, CookieExpiration::SessionEnd));
+    }
+
+    #[test]
+    fn round_trip_osc_cookiejar() {
+        let store = parse_lwp_cookiejar(OSC_COOKIEJAR.as_bytes()).unwrap();
+        let mut written = Vec::new();
+        write_lwp_cookiejar(&store, &mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert_eq!(sorted_lines(&written), sorted_lines(OSC_COOKIEJAR));
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        assert!(parse_lwp_cookiejar("# Netscape HTTP Cookie File\n".as_bytes()).is_err());
+        assert_eq!(
+            parse_lwp_cookiejar("".as_bytes())
+                .unwrap()
+                .iter_any()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn missing_file_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = load_lwp_cookiejar(&dir.path().join("missing")).unwrap();
+        assert_eq!(store.iter_any().count(), 0);
+    }
+
+    #[test]
+    fn save_merges_and_replaces_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("osc").join("cookiejar");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, OSC_COOKIEJAR).unwrap();
+
+        let url = Url::parse("https://api.example.org/").unwrap();
+        let mut store = CookieStore::default();
+        store
+            .parse("new_session=123; Secure; Path=/", &url)
+            .unwrap();
+        save_lwp_cookiejar(&store, &path).unwrap();
+
+        let saved = load_lwp_cookiejar(&path).unwrap();
+        assert_eq!(saved.iter_any().count(), 4);
+        assert_eq!(
+            cookie(&saved, "api.example.org", "new_session").value(),
+            "123"
+        );
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let mut leftovers: Vec<_> = std::fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        leftovers.sort();
+        assert_eq!(leftovers, ["cookiejar", "cookiejar.lock"]);
+    }
+
+    #[test]
+    fn save_creates_missing_jar() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cookiejar");
+        let store = parse_lwp_cookiejar(OSC_COOKIEJAR.as_bytes()).unwrap();
+        save_lwp_cookiejar(&store, &path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(sorted_lines(&content), sorted_lines(OSC_COOKIEJAR));
+    }
+}
@@ -1,3 +1,6 @@
+use std::path::PathBuf;
+
+use reqwest::StatusCode;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -8,6 +11,83 @@ pub enum APIError {
     HTTPError(#[from] reqwest::Error),
     #[error("Unable to parse CookieJar file")]
     CookieError(#[from] cookie_store::CookieError),
+    #[error("Unable to save CookieJar file")]
+    CookieSaveError(#[source] std::io::Error),
     #[error("Invalid object given")]
     InvalidObject,
+    #[error("Unable to parse URL")]
+    URLParseError(#[from] url::ParseError),
+    #[error("Unable to authenticate")]
+    AuthError(#[from] AuthError),
+    #[error("Missing value in OBS configuration: {0}")]
+    MissingConfiguration(&'static str),
+    #[error("Invalid request search: {0}")]
+    InvalidQuery(String),
+    #[error("OBS returned {http_status}: {code}")]
+    OBSStatus {
+        http_status: StatusCode,
+        code: String,
+        summary: Option<String>,
+        details: Option<String>,
+    },
+}
+
+impl APIError {
+    /// The OBS status code (e.g. `request_already_exists`) if OBS gave one
+    pub fn obs_code(&self) -> Option<&str> {
+        match self {
+            APIError::OBSStatus { code, .. } => Some(code),
+            _ => None,
+        }
+    }
+
+    pub fn http_status(&self) -> Option<StatusCode> {
+        match self {
+            APIError::OBSStatus { http_status, .. } => Some(*http_status),
+            APIError::HTTPError(e) => e.status(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ClientError {
+    #[error("Missing client option: {0}")]
+    MissingOption(&'static str),
+    #[error("Host not found in configuration: {0}")]
+    UnknownHost(String),
+    #[error("Unable to load SSH key")]
+    SSHKeyError(#[from] ssh_key::Error),
+    #[error("Unable to parse CookieJar file")]
+    CookieError(#[from] cookie_store::CookieError),
+    #[error("Unable to load certificates from {}: {1}", .0.display())]
+    CertificateError(PathBuf, String),
+    #[error("Unable to create HTTP client")]
+    HTTPError(#[from] reqwest::Error),
+}
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum AuthError {
+    #[error("Unable to ask for password")]
+    AskPassError(#[from] std::io::Error),
+    #[error("Password is not valid UTF-8")]
+    InvalidPassword(#[from] std::string::FromUtf8Error),
+    #[error("No password found for {0}")]
+    MissingPassword(String),
+    #[error("Unable to query password store")]
+    DBusError(#[from] zbus::Error),
+    #[error("Unable to sign with SSH key")]
+    SSHKeyError(#[from] ssh_key::Error),
+    #[error("Invalid authorization header")]
+    InvalidHeader(#[from] reqwest::header::InvalidHeaderValue),
+}
+
+impl From<dialoguer::Error> for AuthError {
+    fn from(error: dialoguer::Error) -> Self {
+        match error {
+            dialoguer::Error::IO(e) => AuthError::AskPassError(e),
+        }
+    }
 }
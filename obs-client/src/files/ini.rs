@@ -0,0 +1,133 @@
+use std::ops::Range;
+
+/// Line based view of an ini file, editing values in place so comments, ordering
+/// and keys we don't know about survive a rewrite.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IniDocument {
+    lines: Vec<String>,
+}
+
+fn section_name(line: &str) -> Option<&str> {
+    let line = line.trim();
+    Some(line.strip_prefix('[')?.split_once(']')?.0.trim())
+}
+
+fn entry_key(line: &str) -> Option<&str> {
+    if line.starts_with(char::is_whitespace) || line.starts_with(['#', ';']) {
+        return None;
+    }
+    Some(line.split_once('=')?.0.trim())
+}
+
+impl IniDocument {
+    pub(crate) fn parse(input: &str) -> Self {
+        Self {
+            lines: input.lines().map(str::to_string).collect(),
+        }
+    }
+
+    /// Names of the sections, as written in the file
+    pub(crate) fn sections(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().filter_map(|l| section_name(l))
+    }
+
+    /// Lines of the section body, header excluded
+    fn section(&self, section: &str) -> Option<Range<usize>> {
+        let start = self
+            .lines
+            .iter()
+            .position(|l| section_name(l).is_some_and(|s| s.eq_ignore_ascii_case(section)))?
+            + 1;
+        let end = self.lines[start..]
+            .iter()
+            .position(|l| section_name(l).is_some())
+            .map_or(self.lines.len(), |i| start + i);
+        Some(start..end)
+    }
+
+    /// Lines of the entry, including its indented continuation lines
+    fn entry(&self, section: &Range<usize>, key: &str) -> Option<Range<usize>> {
+        let start = section
+            .clone()
+            .find(|&i| entry_key(&self.lines[i]).is_some_and(|k| k.eq_ignore_ascii_case(key)))?;
+        let mut end = start + 1;
+        for i in start + 1..section.end {
+            let line = &self.lines[i];
+            if line.trim().is_empty() {
+                continue;
+            }
+            if !line.starts_with(char::is_whitespace) {
+                break;
+            }
+            end = i + 1;
+        }
+        Some(start..end)
+    }
+
+    /// Set `key` in `section`, creating both if needed. Multiline values are written
+    /// as indented continuation lines.
+    pub(crate) fn set(&mut self, section: &str, key: &str, value: &str) {
+        let mut lines = value.lines();
+        let mut entry = vec![match lines.next() {
+            Some(first) if !first.is_empty() => format!("{} = {}", key, first),
+            _ => format!("{} =", key),
+        }];
+        entry.extend(lines.map(|l| format!("  {}", l)));
+
+        let range = match self.section(section) {
+            Some(range) => range,
+            None => {
+                if self.lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                    self.lines.push(String::new());
+                }
+                self.lines.push(format!("[{}]", section));
+                self.lines.len()..self.lines.len()
+            }
+        };
+        match self.entry(&range, key) {
+            Some(existing) => {
+                self.lines.splice(existing, entry);
+            }
+            None => {
+                // Append after the last meaningful line so blank separators stay at the end
+                let at = range
+                    .clone()
+                    .rev()
+                    .find(|&i| !self.lines[i].trim().is_empty())
+                    .map_or(range.start, |i| i + 1);
+                self.lines.splice(at..at, entry);
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, section: &str, key: &str) {
+        if let Some(range) = self.section(section) {
+            if let Some(entry) = self.entry(&range, key) {
+                self.lines.drain(entry);
+            }
+        }
+    }
+
+    /// Set `key` to `value`, or remove it if there is no value
+    pub(crate) fn set_or_remove(&mut self, section: &str, key: &str, value: Option<&str>) {
+        match value {
+            Some(value) => self.set(section, key, value),
+            None => self.remove(section, key),
+        }
+    }
+
+    pub(crate) fn remove_section(&mut self, section: &str) {
+        if let Some(range) = self.section(section) {
+            self.lines.drain(range.start - 1..range.end);
+        }
+    }
+}
+
+impl std::fmt::Display for IniDocument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
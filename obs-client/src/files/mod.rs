@@ -1,5 +1,6 @@
 mod containerinfo;
 mod helminfo;
+mod ini;
 mod oscrc;
 
 pub use containerinfo::ContainerInfo;
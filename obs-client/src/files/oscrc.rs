@@ -1,13 +1,19 @@
 use std::{
     collections::HashMap,
+    ffi::OsString,
+    fs::OpenOptions,
+    io::{Read, Write},
+    os::unix::fs::OpenOptionsExt,
     path::{Path, PathBuf},
     str::FromStr,
 };
 
+use base64::{prelude::BASE64_STANDARD, Engine};
 use configparser::ini::{Ini, IniDefault};
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use url::Url;
 
+use super::ini::IniDocument;
 use crate::authentication::IntoPassword;
 
 const GENERAL_SECTION: &str = "general";
@@ -22,13 +28,24 @@ pub struct Oscrc {
     pub cookiejar: PathBuf,
     pub realname: Option<String>,
     pub email: Option<String>,
+    /// Legacy switch making hosts without `credentials_mgr_class` use the keyring
+    pub use_keyring: bool,
+    pub http_debug: bool,
+    /// When disabled, hosts without `credentials_mgr_class` keep their password
+    /// obfuscated in `passx`
+    pub plaintext_passwd: bool,
+
+    path: Option<PathBuf>,
+    document: IniDocument,
 }
 
+#[derive(Default)]
 pub struct HostOptions {
     pub aliases: Vec<String>,
     pub username: String,
     pub credential_class: CredentialsManagers,
     pub password: Option<String>,
+    pub token: Option<String>,
     pub sshkey: Option<String>,
     pub cafile: Option<PathBuf>,
     pub capath: Option<PathBuf>,
@@ -49,10 +66,78 @@ impl Default for Oscrc {
             cookiejar: bd.get_state_file("cookiejar"),
             realname: None,
             email: None,
+            use_keyring: false,
+            http_debug: false,
+            plaintext_passwd: true,
+
+            path: None,
+            document: IniDocument::default(),
         }
     }
 }
 
+/// Value of an environment variable, `HOME` falling back to the user database
+fn process_env(name: &str) -> Option<OsString> {
+    match name {
+        #[allow(deprecated)]
+        "HOME" => std::env::home_dir().map(PathBuf::into_os_string),
+        _ => std::env::var_os(name),
+    }
+}
+
+fn home_dir(env: &impl Fn(&str) -> Option<OsString>) -> Result<PathBuf, ParseError> {
+    env("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| ParseError::FileError("Unable to find the home directory".to_string()))
+}
+
+/// `$XDG_CONFIG_HOME/osc`, `~/.config/osc` unless set to an absolute path
+fn xdg_config_home(env: &impl Fn(&str) -> Option<OsString>) -> Result<PathBuf, ParseError> {
+    let config_home = match env("XDG_CONFIG_HOME").map(PathBuf::from) {
+        Some(path) if path.is_absolute() => path,
+        _ => home_dir(env)?.join(".config"),
+    };
+    Ok(config_home.join("osc"))
+}
+
+/// [`Oscrc::config_path`], looked up in the `env` variables
+fn resolve_config_path(env: &impl Fn(&str) -> Option<OsString>) -> Result<PathBuf, ParseError> {
+    if let Some(path) = env("OSC_CONFIG") {
+        return Ok(path.into());
+    }
+    let legacy = home_dir(env)?.join(".oscrc");
+    if legacy.symlink_metadata().is_ok() {
+        return Ok(legacy);
+    }
+    Ok(xdg_config_home(env)?.join("oscrc"))
+}
+
+/// Files the configuration is read from, the last one taking precedence: the override or
+/// legacy file alone, else `osc/oscrc` in each of `$XDG_CONFIG_DIRS` (`/etc/xdg` if unset),
+/// least important first, then in `$XDG_CONFIG_HOME`
+fn resolve_config_files(
+    env: &impl Fn(&str) -> Option<OsString>,
+) -> Result<Vec<PathBuf>, ParseError> {
+    let path = resolve_config_path(env)?;
+    if path != xdg_config_home(env)?.join("oscrc") {
+        return Ok(vec![path]);
+    }
+    let config_dirs: Vec<PathBuf> = env("XDG_CONFIG_DIRS")
+        .map(|dirs| {
+            std::env::split_paths(&dirs)
+                .filter(|dir| dir.is_absolute())
+                .collect()
+        })
+        .filter(|dirs: &Vec<PathBuf>| !dirs.is_empty())
+        .unwrap_or_else(|| vec![PathBuf::from("/etc/xdg")]);
+    Ok(config_dirs
+        .iter()
+        .rev()
+        .map(|dir| dir.join("osc/oscrc"))
+        .chain([path])
+        .collect())
+}
+
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
 pub enum ParseError {
@@ -68,30 +153,59 @@ pub enum ParseError {
     URLParseError(#[from] url::ParseError),
     #[error("Unable to read file")]
     FileError(String),
+    #[error("Unable to write file")]
+    WriteError(#[from] std::io::Error),
 }
 
 impl Oscrc {
+    /// Locate the configuration file the way osc does: `OSC_CONFIG` if set, then the
+    /// legacy `~/.oscrc` if it exists, else `$XDG_CONFIG_HOME/osc/oscrc`
+    pub fn config_path() -> Result<PathBuf, ParseError> {
+        resolve_config_path(&process_env)
+    }
+
+    /// Load the configuration from `path_override` or from the files osc reads: the
+    /// [`Oscrc::config_path`] override or legacy file, else the `osc/oscrc` files found in
+    /// `$XDG_CONFIG_DIRS` and `$XDG_CONFIG_HOME`, the last one taking precedence.
+    /// A missing default configuration file gives the default configuration.
     pub fn new(path_override: Option<&Path>) -> Result<Self, ParseError> {
-        let cfgfiles = match path_override {
-            Some(file) => vec![file.to_path_buf()],
-            None => {
-                let bd = xdg::BaseDirectories::with_prefix("osc")?;
-                bd.find_config_files("oscrc").collect()
-            }
-        };
+        match path_override {
+            Some(file) => Self::load(file.to_path_buf(), vec![file.to_path_buf()], true),
+            None => Self::from_env(&process_env),
+        }
+    }
+
+    /// Load the default configuration files, looked up in the `env` variables
+    fn from_env(env: &impl Fn(&str) -> Option<OsString>) -> Result<Self, ParseError> {
+        Self::load(resolve_config_path(env)?, resolve_config_files(env)?, false)
+    }
+
+    /// Read `files` in order, keeping `path` as the document saved back
+    fn load(path: PathBuf, files: Vec<PathBuf>, must_exist: bool) -> Result<Self, ParseError> {
         let mut defaults = IniDefault::default();
         defaults.multiline = true;
         defaults.delimiters = vec!['='];
         let mut cfg = Ini::new_from_defaults(defaults);
-        for file in cfgfiles {
-            cfg.load_and_append(file).map_err(ParseError::FileError)?;
+        // Only the file saved to is kept as document, system wide files are left alone
+        let mut content = String::new();
+        for file in &files {
+            let file_content = match std::fs::read_to_string(file) {
+                Ok(content) => content,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound && !must_exist => continue,
+                Err(e) => return Err(ParseError::FileError(e.to_string())),
+            };
+            cfg.read_and_append(file_content.clone())
+                .map_err(ParseError::FileError)?;
+            if *file == path {
+                content = file_content;
+            }
         }
 
-        let mut config = Self::default();
-        if let Some(apiurl) = cfg.get(GENERAL_SECTION, "apiurl") {
-            let apiurl = Url::parse(&apiurl)?;
-            config.apiurl = apiurl;
-        }
+        let mut config = Self {
+            path: Some(path),
+            document: IniDocument::parse(&content),
+            ..Self::default()
+        };
         config.sshkey = cfg.get(GENERAL_SECTION, "sshkey");
         if let Some(http_retries) =
             cfg.getuint(GENERAL_SECTION, "http_retries")
@@ -108,21 +222,54 @@ impl Oscrc {
         }
         config.realname = cfg.get(GENERAL_SECTION, "realname");
         config.email = cfg.get(GENERAL_SECTION, "email");
+        config.use_keyring = get_bool(&cfg, GENERAL_SECTION, "use_keyring")?.unwrap_or(false);
+        config.http_debug = get_bool(&cfg, GENERAL_SECTION, "http_debug")?.unwrap_or(false);
+        config.plaintext_passwd =
+            get_bool(&cfg, GENERAL_SECTION, "plaintext_passwd")?.unwrap_or(true);
+        let apiurl = cfg.get(GENERAL_SECTION, "apiurl");
         cfg.remove_section(GENERAL_SECTION);
         for section in cfg.sections() {
-            let url = Url::parse(&section)?;
+            let url = parse_apiurl(&section)?;
+            let passx = cfg.get(&section, "passx");
+            let credential_class = match cfg.get(&section, "credentials_mgr_class") {
+                Some(class) => class.into(),
+                None if config.use_keyring => CredentialsManagers::SecretService,
+                None if passx.is_some() || !config.plaintext_passwd => {
+                    CredentialsManagers::ObfuscatedConfigFile
+                }
+                None => CredentialsManagers::Plaintext,
+            };
+            let password = match passx {
+                Some(passx) => {
+                    Some(
+                        passx_decode(&passx).map_err(|error| ParseError::FieldError {
+                            section: section.clone(),
+                            field: "passx",
+                            error,
+                        })?,
+                    )
+                }
+                None => cfg
+                    .get(&section, "pass")
+                    .or_else(|| cfg.get(&section, "password")),
+            };
             let hopts = HostOptions {
-                aliases: vec![],
+                aliases: cfg
+                    .get(&section, "aliases")
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|a| !a.is_empty())
+                    .map(str::to_string)
+                    .collect(),
                 username: cfg.get(&section, "user").ok_or(ParseError::FieldError {
                     section: section.clone(),
                     field: "user",
                     error: "Not found".to_string(),
                 })?,
-                credential_class: cfg
-                    .get(&section, "credentials_mgr_class")
-                    .unwrap_or_default()
-                    .into(),
-                password: cfg.get(&section, "password"),
+                credential_class,
+                password,
+                token: cfg.get(&section, "token"),
                 sshkey: cfg.get(&section, "sshkey"),
                 cafile: cfg.get(&section, "cafile").map(PathBuf::from),
                 capath: cfg.get(&section, "capath").map(PathBuf::from),
@@ -132,49 +279,209 @@ impl Oscrc {
             };
             config.hosts_options.insert(url, hopts);
         }
+        // Like osc, the default apiurl may be given as one of the host aliases
+        if let Some(apiurl) = apiurl {
+            config.apiurl = match config.alias_to_apiurl(&apiurl) {
+                Some(url) => url,
+                None => parse_apiurl(&apiurl)?,
+            };
+        }
 
         Ok(config)
     }
 
-    pub fn apiurl_from_alias(&self, alias: &str) -> Option<Url> {
-        match Url::parse(alias) {
-            Ok(u) => match self.hosts_options.contains_key(&u) {
-                true => Some(u),
+    fn alias_to_apiurl(&self, alias: &str) -> Option<Url> {
+        self.hosts_options.iter().find_map(|(u, opt)| {
+            match opt.aliases.iter().any(|a| a == alias) {
+                true => Some(u.clone()),
                 false => None,
-            },
-            Err(_) => self.hosts_options.iter().find_map(|(u, opt)| {
-                match opt.aliases.contains(&alias.to_string()) {
-                    true => Some(u.clone()),
-                    false => None,
-                }
-            }),
+            }
+        })
+    }
+
+    /// Resolve a host alias or an API url, with or without scheme, to a configured host
+    pub fn apiurl_from_alias(&self, alias: &str) -> Option<Url> {
+        if let Some(url) = self.alias_to_apiurl(alias) {
+            return Some(url);
+        }
+        parse_apiurl(alias)
+            .ok()
+            .filter(|u| self.hosts_options.contains_key(u))
+    }
+
+    /// Change the default API url, written as `apiurl` in the `[general]` section
+    pub fn set_apiurl(&mut self, apiurl: Url) {
+        self.document.set(
+            GENERAL_SECTION,
+            "apiurl",
+            apiurl.as_str().trim_end_matches('/'),
+        );
+        self.apiurl = apiurl;
+    }
+
+    /// Add or replace the section of a host. Keys not managed through [`HostOptions`]
+    /// are left untouched in an existing section.
+    pub fn set_host(&mut self, api_url: Url, options: HostOptions) {
+        let section = self.section_name(&api_url);
+        let aliases = options.aliases.join(",");
+        let http_headers = options
+            .http_headers
+            .iter()
+            .filter_map(|(k, v)| Some(format!("\n{}: {}", k, v.to_str().ok()?)))
+            .collect::<String>();
+        let doc = &mut self.document;
+        doc.set(&section, "user", &options.username);
+        doc.set_or_remove(
+            &section,
+            "aliases",
+            Some(aliases.as_str()).filter(|a| !a.is_empty()),
+        );
+        doc.set(
+            &section,
+            "credentials_mgr_class",
+            options.credential_class.class_name(),
+        );
+        doc.remove(&section, "password");
+        match options.credential_class {
+            CredentialsManagers::ObfuscatedConfigFile => {
+                let passx = options.password.as_deref().map(passx_encode);
+                doc.remove(&section, "pass");
+                doc.set_or_remove(&section, "passx", passx.as_deref());
+            }
+            _ => {
+                doc.remove(&section, "passx");
+                doc.set_or_remove(&section, "pass", options.password.as_deref());
+            }
+        }
+        doc.set_or_remove(&section, "token", options.token.as_deref());
+        doc.set_or_remove(&section, "sshkey", options.sshkey.as_deref());
+        doc.set_or_remove(
+            &section,
+            "cafile",
+            options.cafile.as_deref().and_then(Path::to_str),
+        );
+        doc.set_or_remove(
+            &section,
+            "capath",
+            options.capath.as_deref().and_then(Path::to_str),
+        );
+        doc.set_or_remove(&section, "realname", options.realname.as_deref());
+        doc.set_or_remove(&section, "email", options.email.as_deref());
+        doc.set_or_remove(
+            &section,
+            "http_headers",
+            Some(http_headers.as_str()).filter(|h| !h.is_empty()),
+        );
+        self.hosts_options.insert(api_url, options);
+    }
+
+    pub fn remove_host(&mut self, api_url: &Url) -> Option<HostOptions> {
+        let section = self.section_name(api_url);
+        self.document.remove_section(&section);
+        self.hosts_options.remove(api_url)
+    }
+
+    /// Section of the host as written in the file, or the url without trailing slash
+    /// as osc writes it for new hosts
+    fn section_name(&self, api_url: &Url) -> String {
+        self.document
+            .sections()
+            .find(|s| parse_apiurl(s).is_ok_and(|u| &u == api_url))
+            .map(str::to_string)
+            .unwrap_or_else(|| api_url.as_str().trim_end_matches('/').to_string())
+    }
+
+    /// Write the changes made through [`Oscrc::set_apiurl`], [`Oscrc::set_host`] and
+    /// [`Oscrc::remove_host`] back to the file the configuration was loaded from,
+    /// keeping its comments and unknown keys.
+    pub fn save(&self) -> Result<(), ParseError> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => Self::config_path()?,
+        };
+        self.save_to(&path)
+    }
+
+    /// Write the configuration to `path`, replacing the file atomically. The file may
+    /// hold passwords, so it is always left readable by its owner only.
+    pub fn save_to(&self, path: &Path) -> Result<(), ParseError> {
+        // Replace the target of a symlinked configuration, not the link itself
+        let path = match path.canonicalize() {
+            Ok(path) => path,
+            Err(_) => path.to_path_buf(),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+        temp_name.push(format!(".{}.tmp", std::process::id()));
+        let temp_path = path.with_file_name(temp_name);
+        let result = (|| {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(&temp_path)?;
+            file.write_all(self.document.to_string().as_bytes())?;
+            file.sync_all()?;
+            std::fs::rename(&temp_path, &path)
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
         }
+        Ok(result?)
     }
 
-    pub fn get_password_provider(&self, api_url: &Url) -> Box<dyn IntoPassword> {
-        let host_options = &self.hosts_options[api_url];
-        match host_options.credential_class {
-            CredentialsManagers::Plaintext => {
+    /// Password of the host from its credentials manager. With `store_prompted`, a password
+    /// asked for because the manager has none is stored in it, only the kernel keyring
+    /// supports this.
+    pub fn get_password_provider(
+        &self,
+        api_url: &Url,
+        store_prompted: bool,
+    ) -> Option<Box<dyn IntoPassword>> {
+        let host_options = self.hosts_options.get(api_url)?;
+        Some(match host_options.credential_class {
+            CredentialsManagers::Plaintext | CredentialsManagers::ObfuscatedConfigFile => {
                 Box::new(host_options.password.clone().unwrap_or_default())
             }
             CredentialsManagers::Transient => Box::new(crate::authentication::askpass),
+            #[cfg(target_os = "linux")]
+            CredentialsManagers::KernelKeyring => Box::new(
+                crate::kernel_keyring::KernelKeyringGetter::new(api_url, &host_options.username)
+                    .with_store_prompted(store_prompted),
+            ),
+            #[cfg(not(target_os = "linux"))]
             CredentialsManagers::KernelKeyring => {
                 tracing::warn!(
-                    "Kernel Keyring backend not implemented yet, falling back to Transient"
+                    "Kernel Keyring backend only available on Linux, falling back to Transient"
                 );
                 Box::new(crate::authentication::askpass)
             }
-            CredentialsManagers::SecretService => {
+            CredentialsManagers::SecretService => Box::new(
+                crate::secret_service::SecretServiceGetter::new(api_url, &host_options.username),
+            ),
+            CredentialsManagers::Kwallet => Box::new(crate::kwallet::KWalletGetter::new(
+                api_url,
+                &host_options.username,
+            )),
+            CredentialsManagers::Unsupported(ref class) => {
                 tracing::warn!(
-                    "Secret Service backend not implemented yet, falling back to Transient"
+                    class,
+                    "Unsupported credentials manager, falling back to Transient"
                 );
                 Box::new(crate::authentication::askpass)
             }
-            CredentialsManagers::Kwallet => Box::new(crate::kwallet::KWalletGetter::new(
-                api_url,
-                &self.hosts_options[api_url].username,
-            )),
-        }
+        })
+    }
+}
+
+/// Service name osc gives to python-keyring: the network location of the API url
+pub(crate) fn keyring_service(api_url: &Url) -> String {
+    match api_url.port() {
+        Some(port) => format!("{}:{}", api_url.host_str().unwrap_or_default(), port),
+        None => api_url.host_str().unwrap_or_default().to_string(),
     }
 }
 
@@ -182,9 +489,13 @@ impl Oscrc {
 pub enum CredentialsManagers {
     Transient,
     Plaintext,
+    /// Password kept in `passx`, bzip2 compressed and base64 encoded
+    ObfuscatedConfigFile,
     KernelKeyring,
     SecretService,
     Kwallet,
+    /// Any other class, its password is asked for instead
+    Unsupported(String),
 }
 
 impl Default for CredentialsManagers {
@@ -193,10 +504,33 @@ impl Default for CredentialsManagers {
     }
 }
 
+impl CredentialsManagers {
+    /// Value of `credentials_mgr_class` selecting this manager in osc
+    pub fn class_name(&self) -> &str {
+        match self {
+            Self::Transient => "osc.credentials.TransientCredentialsManager",
+            Self::Plaintext => "osc.credentials.PlaintextConfigFileCredentialsManager",
+            Self::ObfuscatedConfigFile => "osc.credentials.ObfuscatedConfigFileCredentialsManager",
+            Self::KernelKeyring => {
+                "osc.credentials.KeyringCredentialsManager:keyutils.osc.OscKernelKeyringBackend"
+            }
+            Self::SecretService => {
+                "osc.credentials.KeyringCredentialsManager:keyring.backends.SecretService.Keyring"
+            }
+            Self::Kwallet => {
+                "osc.credentials.KeyringCredentialsManager:keyring.backends.kwallet.DBusKeyring"
+            }
+            Self::Unsupported(class) => class,
+        }
+    }
+}
+
 impl From<String> for CredentialsManagers {
     fn from(value: String) -> Self {
         match value.as_str() {
             "osc.credentials.TransientCredentialsManager" => Self::Transient,
+            "osc.credentials.PlaintextConfigFileCredentialsManager" => Self::Plaintext,
+            "osc.credentials.ObfuscatedConfigFileCredentialsManager" => Self::ObfuscatedConfigFile,
             "osc.credentials.KeyringCredentialsManager:keyring.backends.kwallet.DBusKeyring" => {
                 Self::Kwallet
             }
@@ -206,19 +540,548 @@ impl From<String> for CredentialsManagers {
             "osc.credentials.KeyringCredentialsManager:keyring.backends.SecretService.Keyring" => {
                 Self::SecretService
             }
-            _ => Self::Plaintext,
+            _ => Self::Unsupported(value),
         }
     }
 }
 
+/// Decode a `passx` value, as written by osc's `ObfuscatedConfigFileCredentialsManager`
+fn passx_decode(passx: &str) -> Result<String, String> {
+    let compressed = BASE64_STANDARD
+        .decode(passx.trim())
+        .map_err(|e| e.to_string())?;
+    let mut password = String::new();
+    bzip2::read::BzDecoder::new(compressed.as_slice())
+        .read_to_string(&mut password)
+        .map_err(|e| e.to_string())?;
+    Ok(password)
+}
+
+fn passx_encode(password: &str) -> String {
+    let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::best());
+    encoder
+        .write_all(password.as_bytes())
+        .expect("writing to a Vec can't fail");
+    BASE64_STANDARD.encode(encoder.finish().expect("writing to a Vec can't fail"))
+}
+
+/// Hosts may be written without scheme, osc then assumes https
+fn parse_apiurl(input: &str) -> Result<Url, url::ParseError> {
+    match input.contains("://") {
+        true => Url::parse(input),
+        false => Url::parse(&format!("https://{}", input)),
+    }
+}
+
+fn get_bool(cfg: &Ini, section: &str, field: &'static str) -> Result<Option<bool>, ParseError> {
+    cfg.getboolcoerce(section, field)
+        .map_err(|error| ParseError::FieldError {
+            section: section.to_string(),
+            field,
+            error,
+        })
+}
+
 fn parse_headers(input: &str) -> HeaderMap {
     input
         .lines()
         .filter_map(|line| line.split_once(':'))
         .filter_map(|(k, v)| {
-            let k = HeaderName::from_str(k).ok()?;
-            let v = HeaderValue::from_str(v).ok()?;
+            let k = HeaderName::from_str(k.trim()).ok()?;
+            let v = HeaderValue::from_str(v.trim()).ok()?;
             Some((k, v))
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    /// `passx` osc writes for the password `obfuscated`
+    const PASSX: &str = "QlpoOTFBWSZTWVCJYvMAAAMBgD8AjgAgADEAMCAMJYIQLT8XckU4UJBQiWLz";
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/oscrc")
+            .join(name)
+    }
+
+    /// Copy of a fixture in a temporary directory
+    fn fixture_copy(name: &str) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("oscrc");
+        std::fs::copy(fixture(name), &path).unwrap();
+        (dir, path)
+    }
+
+    fn url(input: &str) -> Url {
+        Url::parse(input).unwrap()
+    }
+
+    #[test]
+    fn unchanged_save_is_identical() {
+        let (_dir, path) = fixture_copy("template.oscrc");
+        Oscrc::new(Some(&path)).unwrap().save().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            std::fs::read_to_string(fixture("template.oscrc")).unwrap()
+        );
+    }
+
+    #[test]
+    fn add_host_keeps_comments_and_other_sections() {
+        let (_dir, path) = fixture_copy("template.oscrc");
+        let original = std::fs::read_to_string(&path).unwrap();
+        let mut cfg = Oscrc::new(Some(&path)).unwrap();
+        cfg.set_host(
+            url("https://api.example.org"),
+            HostOptions {
+                aliases: vec!["example".to_string(), "ex".to_string()],
+                username: "onboard".to_string(),
+                credential_class: CredentialsManagers::SecretService,
+                sshkey: Some("id_ed25519.pub".to_string()),
+                ..Default::default()
+            },
+        );
+        cfg.save().unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            saved.strip_prefix(original.as_str()).unwrap(),
+            "\n[https://api.example.org]\n\
+             user = onboard\n\
+             aliases = example,ex\n\
+             credentials_mgr_class = osc.credentials.KeyringCredentialsManager:keyring.backends.SecretService.Keyring\n\
+             sshkey = id_ed25519.pub\n"
+        );
+
+        let cfg = Oscrc::new(Some(&path)).unwrap();
+        let api_url = cfg.apiurl_from_alias("ex").unwrap();
+        assert_eq!(api_url, url("https://api.example.org"));
+        let host = &cfg.hosts_options[&api_url];
+        assert_eq!(host.username, "onboard");
+        assert_eq!(host.sshkey.as_deref(), Some("id_ed25519.pub"));
+        assert!(matches!(
+            host.credential_class,
+            CredentialsManagers::SecretService
+        ));
+        assert_eq!(cfg.hosts_options.len(), 3);
+    }
+
+    #[test]
+    fn update_host_keeps_unknown_keys() {
+        let (_dir, path) = fixture_copy("template.oscrc");
+        let mut cfg = Oscrc::new(Some(&path)).unwrap();
+        let api_url = url("https://api.suse.de");
+        let mut host = cfg.hosts_options.remove(&api_url).unwrap();
+        assert_eq!(host.http_headers.len(), 2);
+        host.username = "baz".to_string();
+        host.http_headers.remove("X-Other");
+        cfg.set_host(api_url, host);
+        cfg.save().unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        let section = &saved[saved.find("[https://api.suse.de]").unwrap()..];
+        assert_eq!(
+            section,
+            "[https://api.suse.de]\n\
+             credentials_mgr_class = osc.credentials.TransientCredentialsManager\n\
+             user = baz\n\
+             aliases = ibs\n\
+             http_headers =\n  \
+             x-custom: value\n\
+             # osc keeps options it does not know about\n\
+             downloadurl = https://download.suse.de\n"
+        );
+        let cfg = Oscrc::new(Some(&path)).unwrap();
+        assert_eq!(
+            cfg.hosts_options[&url("https://api.suse.de")].username,
+            "baz"
+        );
+    }
+
+    #[test]
+    fn set_apiurl_rewrites_only_that_line() {
+        let (_dir, path) = fixture_copy("template.oscrc");
+        let mut cfg = Oscrc::new(Some(&path)).unwrap();
+        cfg.set_apiurl(url("https://api.suse.de"));
+        cfg.save().unwrap();
+
+        let original = std::fs::read_to_string(fixture("template.oscrc")).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        let changed: Vec<(&str, &str)> = original
+            .lines()
+            .zip(saved.lines())
+            .filter(|(a, b)| a != b)
+            .collect();
+        assert_eq!(
+            changed,
+            [(
+                "apiurl = https://api.opensuse.org",
+                "apiurl = https://api.suse.de"
+            )]
+        );
+        assert_eq!(original.lines().count(), saved.lines().count());
+        assert_eq!(
+            Oscrc::new(Some(&path)).unwrap().apiurl,
+            url("https://api.suse.de")
+        );
+    }
+
+    #[test]
+    fn remove_host_drops_its_section_only() {
+        let (_dir, path) = fixture_copy("template.oscrc");
+        let mut cfg = Oscrc::new(Some(&path)).unwrap();
+        assert!(cfg.remove_host(&url("https://api.suse.de")).is_some());
+        cfg.save().unwrap();
+
+        let original = std::fs::read_to_string(fixture("template.oscrc")).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        let kept = &original[..original.find("[https://api.suse.de]").unwrap()];
+        assert_eq!(saved, kept);
+        assert_eq!(Oscrc::new(Some(&path)).unwrap().hosts_options.len(), 1);
+    }
+
+    #[test]
+    fn save_replaces_file_readable_by_owner_only() {
+        let (dir, path) = fixture_copy("template.oscrc");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        Oscrc::new(Some(&path)).unwrap().save().unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let files: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(files, ["oscrc"]);
+    }
+
+    #[test]
+    fn save_follows_symlinks() {
+        let (dir, target) = fixture_copy("template.oscrc");
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let mut cfg = Oscrc::new(Some(&link)).unwrap();
+        cfg.set_apiurl(url("https://api.suse.de"));
+        cfg.save().unwrap();
+
+        assert!(link.symlink_metadata().unwrap().file_type().is_symlink());
+        assert_eq!(
+            Oscrc::new(Some(&target)).unwrap().apiurl,
+            url("https://api.suse.de")
+        );
+    }
+
+    #[test]
+    fn save_to_creates_missing_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("osc").join("oscrc");
+        let mut cfg = Oscrc::new(Some(&fixture("template.oscrc"))).unwrap();
+        cfg.set_apiurl(url("https://api.suse.de"));
+        cfg.save_to(&path).unwrap();
+        assert_eq!(
+            Oscrc::new(Some(&path)).unwrap().apiurl,
+            url("https://api.suse.de")
+        );
+    }
+
+    fn load(name: &str) -> Oscrc {
+        Oscrc::new(Some(&fixture(name))).unwrap()
+    }
+
+    #[test]
+    fn resolves_aliases_and_general_options() {
+        let cfg = load("resolution.oscrc");
+        let example = url("https://api.example.org");
+        assert_eq!(cfg.apiurl, example);
+        assert_eq!(cfg.hosts_options[&example].aliases, ["ex", "example"]);
+        assert_eq!(cfg.apiurl_from_alias("example"), Some(example.clone()));
+        assert_eq!(
+            cfg.apiurl_from_alias("api.example.org"),
+            Some(example.clone())
+        );
+        assert_eq!(
+            cfg.apiurl_from_alias("https://api.opensuse.org"),
+            Some(url("https://api.opensuse.org"))
+        );
+        assert_eq!(cfg.apiurl_from_alias("api.suse.de"), None);
+        assert_eq!(cfg.apiurl_from_alias("other"), None);
+
+        assert_eq!(cfg.sshkey.as_deref(), Some("id_ed25519.pub"));
+        assert_eq!(cfg.http_retries, 5);
+        assert!(cfg.http_debug);
+        assert!(!cfg.use_keyring);
+        assert!(cfg.plaintext_passwd);
+        assert_eq!(cfg.cookiejar, Path::new("/var/tmp/osc/cookiejar"));
+        assert_eq!(cfg.realname.as_deref(), Some("Jane Doe"));
+        assert_eq!(cfg.email.as_deref(), Some("jane@example.com"));
+
+        let host = &cfg.hosts_options[&example];
+        assert_eq!(host.username, "alice");
+        assert_eq!(host.password.as_deref(), Some("secret"));
+        assert_eq!(host.sshkey.as_deref(), Some("~/.ssh/example.pub"));
+        assert_eq!(
+            host.cafile.as_deref(),
+            Some(Path::new("/etc/ssl/example.pem"))
+        );
+        assert!(matches!(
+            host.credential_class,
+            CredentialsManagers::Plaintext
+        ));
+    }
+
+    #[test]
+    fn apiurl_defaults_to_opensuse() {
+        let cfg = load("passwords.oscrc");
+        assert_eq!(cfg.apiurl, url("https://api.opensuse.org"));
+        assert_eq!(cfg.http_retries, 3);
+        assert!(!cfg.http_debug);
+    }
+
+    #[tokio::test]
+    async fn reads_plaintext_and_obfuscated_passwords() {
+        let cfg = load("passwords.oscrc");
+        assert!(!cfg.plaintext_passwd);
+        let cases = [
+            ("obfuscated", Some("obfuscated"), "ObfuscatedConfigFile"),
+            ("legacy", Some("plain"), "ObfuscatedConfigFile"),
+            ("plaintext", Some("plain"), "PlaintextConfigFile"),
+            ("password", Some("legacy"), "PlaintextConfigFile"),
+            ("explicit", Some("obfuscated"), "ObfuscatedConfigFile"),
+            ("transient", None, "Transient"),
+        ];
+        for (host, password, class) in cases {
+            let api_url = url(&format!("https://{}.example.org", host));
+            let options = &cfg.hosts_options[&api_url];
+            assert_eq!(options.password.as_deref(), password, "{}", host);
+            assert_eq!(
+                options.credential_class.class_name(),
+                format!("osc.credentials.{}CredentialsManager", class),
+                "{}",
+                host
+            );
+            if let Some(password) = password {
+                let provider = cfg.get_password_provider(&api_url, false).unwrap();
+                assert_eq!(provider.pass().await.unwrap(), password);
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_unsupported_credentials_managers() {
+        let cfg = load("passwords.oscrc");
+        let class = &cfg.hosts_options[&url("https://macos.example.org")].credential_class;
+        assert!(matches!(class, CredentialsManagers::Unsupported(_)));
+        assert_eq!(
+            class.class_name(),
+            "osc.credentials.KeyringCredentialsManager:keyring.backends.macOS.Keyring"
+        );
+    }
+
+    #[test]
+    fn use_keyring_selects_secret_service() {
+        let cfg = load("keyring.oscrc");
+        assert!(cfg.use_keyring);
+        let class = |host: &str| &cfg.hosts_options[&url(host)].credential_class;
+        assert!(matches!(
+            class("https://api.example.org"),
+            CredentialsManagers::SecretService
+        ));
+        assert!(matches!(
+            class("https://kernel.example.org"),
+            CredentialsManagers::KernelKeyring
+        ));
+        assert!(matches!(
+            class("https://kwallet.example.org"),
+            CredentialsManagers::Kwallet
+        ));
+    }
+
+    #[test]
+    fn invalid_passx_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("oscrc");
+        std::fs::write(
+            &path,
+            "[https://api.example.org]\nuser = a\npassx = bm90IGJ6aXAy\n",
+        )
+        .unwrap();
+        match Oscrc::new(Some(&path)) {
+            Err(ParseError::FieldError { section, field, .. }) => {
+                assert_eq!(section, "https://api.example.org");
+                assert_eq!(field, "passx");
+            }
+            _ => panic!("passx should not decode"),
+        }
+    }
+
+    #[test]
+    fn passx_matches_osc() {
+        assert_eq!(passx_encode("obfuscated"), PASSX);
+        assert_eq!(passx_decode(PASSX).unwrap(), "obfuscated");
+    }
+
+    #[test]
+    fn writes_obfuscated_passwords_to_passx() {
+        let (_dir, path) = fixture_copy("passwords.oscrc");
+        let mut cfg = Oscrc::new(Some(&path)).unwrap();
+        let api_url = url("https://legacy.example.org");
+        let mut host = cfg.hosts_options.remove(&api_url).unwrap();
+        host.password = Some("obfuscated".to_string());
+        cfg.set_host(api_url.clone(), host);
+        cfg.save().unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(saved.contains(&format!(
+            "[https://legacy.example.org]\n\
+             # written before plaintext_passwd was disabled\n\
+             user = b\n\
+             credentials_mgr_class = osc.credentials.ObfuscatedConfigFileCredentialsManager\n\
+             passx = {}\n",
+            PASSX
+        )));
+        let cfg = Oscrc::new(Some(&path)).unwrap();
+        assert_eq!(
+            cfg.hosts_options[&api_url].password.as_deref(),
+            Some("obfuscated")
+        );
+    }
+
+    /// Environment of a user whose home and XDG directories are in `dir`
+    fn user_env(dir: &Path, osc_config: Option<&Path>) -> impl Fn(&str) -> Option<OsString> {
+        let dir = dir.to_path_buf();
+        let osc_config = osc_config.map(Path::to_path_buf);
+        move |name| match name {
+            "HOME" => Some(dir.join("home").into()),
+            "XDG_CONFIG_HOME" => Some(dir.join("config").into()),
+            "XDG_CONFIG_DIRS" => Some(dir.join("etc").into()),
+            "OSC_CONFIG" => osc_config.clone().map(Into::into),
+            _ => None,
+        }
+    }
+
+    fn write(path: PathBuf, content: &str) -> PathBuf {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn host(user: &str) -> String {
+        format!("[https://api.example.org]\nuser = {}\n", user)
+    }
+
+    fn user(cfg: &Oscrc) -> &str {
+        &cfg.hosts_options[&url("https://api.example.org")].username
+    }
+
+    #[test]
+    fn osc_config_takes_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path().join("home/.oscrc"), &host("legacy"));
+        write(dir.path().join("config/osc/oscrc"), &host("xdg"));
+        let path = write(dir.path().join("custom"), &host("custom"));
+        let env = user_env(dir.path(), Some(&path));
+
+        assert_eq!(resolve_config_path(&env).unwrap(), path);
+        assert_eq!(resolve_config_files(&env).unwrap(), [path]);
+        assert_eq!(user(&Oscrc::from_env(&env).unwrap()), "custom");
+    }
+
+    #[test]
+    fn legacy_oscrc_takes_precedence_over_xdg() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(dir.path().join("home/.oscrc"), &host("legacy"));
+        write(dir.path().join("config/osc/oscrc"), &host("xdg"));
+        let env = user_env(dir.path(), None);
+
+        assert_eq!(resolve_config_path(&env).unwrap(), path);
+        assert_eq!(user(&Oscrc::from_env(&env).unwrap()), "legacy");
+    }
+
+    #[test]
+    fn xdg_config_dirs_are_merged() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path().join("etc/osc/oscrc"),
+            "[general]\nhttp_retries = 7\napiurl = https://api.example.org\n\n\
+             [https://api.example.org]\nuser = system\naliases = ex\n",
+        );
+        let path = write(dir.path().join("config/osc/oscrc"), &host("xdg"));
+        let env = user_env(dir.path(), None);
+
+        assert_eq!(resolve_config_path(&env).unwrap(), path);
+        let mut cfg = Oscrc::from_env(&env).unwrap();
+        assert_eq!(user(&cfg), "xdg");
+        assert_eq!(cfg.http_retries, 7);
+        assert_eq!(
+            cfg.apiurl_from_alias("ex"),
+            Some(url("https://api.example.org"))
+        );
+
+        // Only the user file is written back
+        cfg.set_apiurl(url("https://api.opensuse.org"));
+        cfg.save().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!(
+                "{}\n[general]\napiurl = https://api.opensuse.org\n",
+                host("xdg")
+            )
+        );
+    }
+
+    #[test]
+    fn xdg_defaults_apply_when_unset() {
+        let env = |name: &str| match name {
+            "HOME" => Some("/home/user".into()),
+            // Relative directories are ignored
+            "XDG_CONFIG_HOME" => Some("config".into()),
+            "XDG_CONFIG_DIRS" => Some("etc:".into()),
+            _ => None,
+        };
+        assert_eq!(
+            resolve_config_path(&env).unwrap(),
+            Path::new("/home/user/.config/osc/oscrc")
+        );
+        assert_eq!(
+            resolve_config_files(&env).unwrap(),
+            [
+                Path::new("/etc/xdg/osc/oscrc"),
+                Path::new("/home/user/.config/osc/oscrc")
+            ]
+        );
+
+        let env = |name: &str| match name {
+            "HOME" => Some("/home/user".into()),
+            "XDG_CONFIG_DIRS" => Some("/etc/first:/etc/second".into()),
+            _ => None,
+        };
+        assert_eq!(
+            resolve_config_files(&env).unwrap(),
+            [
+                Path::new("/etc/second/osc/oscrc"),
+                Path::new("/etc/first/osc/oscrc"),
+                Path::new("/home/user/.config/osc/oscrc")
+            ]
+        );
+        assert!(resolve_config_path(&|_: &str| None).is_err());
+    }
+
+    #[test]
+    fn missing_configuration_gives_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = user_env(dir.path(), None);
+        let cfg = Oscrc::from_env(&env).unwrap();
+        assert!(cfg.hosts_options.is_empty());
+        assert_eq!(cfg.apiurl, url("https://api.opensuse.org"));
+        assert_eq!(
+            resolve_config_path(&env).unwrap(),
+            dir.path().join("config/osc/oscrc")
+        );
+        assert!(Oscrc::new(Some(&dir.path().join("missing"))).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use std::{ffi::CString, io};
+
+use url::Url;
+
+use crate::{
+    authentication::{askpass, IntoPassword},
+    error::AuthError,
+};
+
+const KEY_TYPE: &str = "user";
+
+/// Read the password osc stored through `keyutils.osc.OscKernelKeyringBackend`,
+/// a `user` key named `<username>@<service>` in the session keyring.
+#[derive(Debug)]
+pub struct KernelKeyringGetter {
+    description: String,
+    store_prompted: bool,
+}
+
+impl KernelKeyringGetter {
+    pub fn new(api_url: &Url, username: &str) -> Self {
+        Self {
+            description: format!("{}@{}", username, crate::files::keyring_service(api_url)),
+            store_prompted: false,
+        }
+    }
+
+    /// Store the password asked for when the key is missing.
+    ///
+    /// The password is stored before OBS has checked it, a mistyped one stays in the keyring
+    /// until replaced with [`KernelKeyringGetter::set`].
+    pub fn with_store_prompted(mut self, store_prompted: bool) -> Self {
+        self.store_prompted = store_prompted;
+        self
+    }
+
+    /// Look the key up in the keyrings attached to the process, `None` if it does not exist
+    pub fn get(&self) -> io::Result<Option<String>> {
+        let key_type = CString::new(KEY_TYPE)?;
+        let description = CString::new(self.description.as_str())?;
+        // SAFETY: both strings are valid and NUL terminated, no callout info is given
+        let key = unsafe {
+            libc::syscall(
+                libc::SYS_request_key,
+                key_type.as_ptr(),
+                description.as_ptr(),
+                std::ptr::null::<libc::c_char>(),
+                0,
+            )
+        };
+        if key < 0 {
+            let error = io::Error::last_os_error();
+            return match error.raw_os_error() {
+                Some(libc::ENOKEY) | Some(libc::EKEYEXPIRED) | Some(libc::EKEYREVOKED) => Ok(None),
+                _ => Err(error),
+            };
+        }
+        let payload = read_key(key)?;
+        String::from_utf8(payload)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Store the password in the session keyring, replacing any previous value
+    pub fn set(&self, password: &str) -> io::Result<()> {
+        let key_type = CString::new(KEY_TYPE)?;
+        let description = CString::new(self.description.as_str())?;
+        // SAFETY: strings are NUL terminated and the payload length matches the buffer
+        let key = unsafe {
+            libc::syscall(
+                libc::SYS_add_key,
+                key_type.as_ptr(),
+                description.as_ptr(),
+                password.as_ptr(),
+                password.len(),
+                libc::KEY_SPEC_SESSION_KEYRING,
+            )
+        };
+        if key < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+fn read_key(key: libc::c_long) -> io::Result<Vec<u8>> {
+    let mut buffer: Vec<u8> = Vec::new();
+    loop {
+        // SAFETY: the kernel writes at most `buffer.capacity()` bytes into the buffer
+        let size = unsafe {
+            libc::syscall(
+                libc::SYS_keyctl,
+                libc::KEYCTL_READ,
+                key,
+                buffer.as_mut_ptr(),
+                buffer.capacity(),
+            )
+        };
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let size = size as usize;
+        // The returned size is the full payload size, retry if the buffer was too small
+        if size <= buffer.capacity() {
+            // SAFETY: the kernel initialized `size` bytes
+            unsafe { buffer.set_len(size) };
+            return Ok(buffer);
+        }
+        buffer.reserve_exact(size);
+    }
+}
+
+impl KernelKeyringGetter {
+    /// Read the key, calling `prompt` when it is missing or unreadable
+    fn get_or_prompt(
+        &self,
+        prompt: impl FnOnce() -> Result<String, AuthError>,
+    ) -> Result<String, AuthError> {
+        match self.get() {
+            Ok(Some(password)) => return Ok(password),
+            Ok(None) => tracing::warn!(
+                key = self.description,
+                "No password found in kernel keyring, falling back to Transient"
+            ),
+            Err(error) => {
+                tracing::warn!(%error, "Unable to read kernel keyring, falling back to Transient")
+            }
+        }
+        let password = prompt()?;
+        if self.store_prompted {
+            if let Err(error) = self.set(&password) {
+                tracing::warn!(%error, "Unable to store password in kernel keyring");
+            }
+        }
+        Ok(password)
+    }
+}
+
+#[async_trait::async_trait]
+impl IntoPassword for KernelKeyringGetter {
+    async fn pass(&self) -> Result<String, AuthError> {
+        self.get_or_prompt(askpass)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replace the session keyring of the test thread by a new anonymous one
+    fn join_new_session_keyring() {
+        // SAFETY: a NULL name asks for a new anonymous keyring
+        let key = unsafe {
+            libc::syscall(
+                libc::SYS_keyctl,
+                libc::KEYCTL_JOIN_SESSION_KEYRING,
+                std::ptr::null::<libc::c_char>(),
+            )
+        };
+        assert!(key >= 0, "{}", io::Error::last_os_error());
+    }
+
+    #[tokio::test]
+    async fn stores_and_reads_osc_key() {
+        join_new_session_keyring();
+        let getter =
+            KernelKeyringGetter::new(&Url::parse("https://api.example.org").unwrap(), "user");
+        assert_eq!(getter.description, "user@api.example.org");
+        assert_eq!(getter.get().unwrap(), None);
+
+        getter.set("first").unwrap();
+        getter.set("secret").unwrap();
+        assert_eq!(getter.get().unwrap().as_deref(), Some("secret"));
+        assert_eq!(getter.pass().await.unwrap(), "secret");
+    }
+
+    #[test]
+    fn stores_prompted_password_on_request() {
+        join_new_session_keyring();
+        let url = Url::parse("https://api.example.org").unwrap();
+        let getter = KernelKeyringGetter::new(&url, "user");
+        let typed = getter.get_or_prompt(|| Ok("typed".to_string())).unwrap();
+        assert_eq!(typed, "typed");
+        assert_eq!(getter.get().unwrap(), None);
+
+        let getter = KernelKeyringGetter::new(&url, "user").with_store_prompted(true);
+        getter.get_or_prompt(|| Ok("typed".to_string())).unwrap();
+        assert_eq!(getter.get().unwrap().as_deref(), Some("typed"));
+        // The stored key is used from now on
+        let stored = getter
+            .get_or_prompt(|| panic!("the stored key should be used"))
+            .unwrap();
+        assert_eq!(stored, "typed");
+    }
+
+    #[test]
+    fn keys_are_per_host_and_user() {
+        join_new_session_keyring();
+        let url = Url::parse("https://api.example.org:8443").unwrap();
+        KernelKeyringGetter::new(&url, "user")
+            .set("secret")
+            .unwrap();
+
+        let other_user = KernelKeyringGetter::new(&url, "other");
+        assert_eq!(other_user.get().unwrap(), None);
+        let other_port =
+            KernelKeyringGetter::new(&Url::parse("https://api.example.org").unwrap(), "user");
+        assert_eq!(other_port.get().unwrap(), None);
+    }
+}
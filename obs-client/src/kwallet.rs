@@ -2,7 +2,7 @@ use tokio::sync::OnceCell;
 use url::Url;
 use zbus::{proxy, Connection, Result};
 
-use crate::authentication::IntoPassword;
+use crate::{authentication::IntoPassword, error::AuthError};
 
 #[proxy(
     interface = "org.kde.KWallet",
@@ -39,7 +39,7 @@ pub struct KWalletGetter {
 impl KWalletGetter {
     pub fn new(api_url: &Url, username: &str) -> Self {
         Self {
-            folder: api_url.host_str().unwrap().to_string(),
+            folder: api_url.host_str().unwrap_or_default().to_string(),
             key: username.to_string(),
             ..Default::default()
         }
@@ -48,30 +48,30 @@ impl KWalletGetter {
 
 #[async_trait::async_trait]
 impl IntoPassword for KWalletGetter {
-    async fn pass(&self) -> String {
-        let connection = self.connection.get_or_init(get_connection).await;
-        let proxy = KWalletProxy::new(connection).await.unwrap();
+    async fn pass(&self) -> std::result::Result<String, AuthError> {
+        let connection = self.connection.get_or_try_init(Connection::session).await?;
+        let proxy = KWalletProxy::new(connection).await?;
         let handle = self
             .handle
-            .get_or_init(|| async {
-                let wallet = proxy.network_wallet().await.unwrap();
-                proxy.open(&wallet, 0, APP_ID).await.unwrap()
+            .get_or_try_init(|| async {
+                let wallet = proxy.network_wallet().await?;
+                match proxy.open(&wallet, 0, APP_ID).await? {
+                    handle if handle < 0 => Err(AuthError::MissingPassword(wallet)),
+                    handle => Ok(handle),
+                }
             })
-            .await;
+            .await?;
         if !proxy
             .has_entry(*handle, &self.folder, &self.key, APP_ID)
-            .await
-            .unwrap()
+            .await?
         {
-            panic!("No password found: {}/{}", &self.folder, &self.key);
+            return Err(AuthError::MissingPassword(format!(
+                "{}/{}",
+                &self.folder, &self.key
+            )));
         }
-        proxy
+        Ok(proxy
             .read_password(*handle, &self.folder, &self.key, APP_ID)
-            .await
-            .unwrap()
+            .await?)
     }
 }
-
-async fn get_connection() -> Connection {
-    Connection::session().await.unwrap()
-}
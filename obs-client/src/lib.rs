@@ -4,7 +4,17 @@ pub mod client;
 mod cookies;
 pub mod error;
 pub mod files;
+pub mod retry;
+mod ssh_agent;
+#[cfg(test)]
+mod test_utils;
+mod tls;
 
+#[cfg(target_os = "linux")]
+mod kernel_keyring;
 mod kwallet;
+mod secret_service;
 
 pub use cookies::get_osc_cookiejar;
+#[cfg(target_os = "linux")]
+pub use kernel_keyring::KernelKeyringGetter;
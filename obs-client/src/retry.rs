@@ -0,0 +1,234 @@
+use std::time::{Duration, SystemTime};
+
+use reqwest::{header::RETRY_AFTER, Method, Response, StatusCode};
+
+use crate::files::Oscrc;
+
+/// Controls how [`OBSClient`](crate::client::OBSClient) retries transient failures
+/// (connection errors, 5xx gateway errors and rate limiting).
+///
+/// Delays grow exponentially from `base_delay` up to `max_delay`, with full jitter.
+/// A `Retry-After` header sent by the server takes precedence over the computed delay,
+/// capped to `max_delay`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u64,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl From<&Oscrc> for RetryPolicy {
+    fn from(value: &Oscrc) -> Self {
+        Self {
+            max_retries: value.http_retries,
+            ..Default::default()
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy never retrying any request
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u64) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_delays(mut self, base_delay: Duration, max_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub(crate) fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(self.max_delay);
+        }
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        ceiling.mul_f64(fastrand::f64())
+    }
+}
+
+pub(crate) fn is_idempotent(method: &Method) -> bool {
+    [
+        Method::GET,
+        Method::HEAD,
+        Method::PUT,
+        Method::DELETE,
+        Method::OPTIONS,
+        Method::TRACE,
+    ]
+    .contains(method)
+}
+
+pub(crate) fn is_transient_status(status: StatusCode) -> bool {
+    [
+        StatusCode::REQUEST_TIMEOUT,
+        StatusCode::TOO_MANY_REQUESTS,
+        StatusCode::INTERNAL_SERVER_ERROR,
+        StatusCode::BAD_GATEWAY,
+        StatusCode::SERVICE_UNAVAILABLE,
+        StatusCode::GATEWAY_TIMEOUT,
+    ]
+    .contains(&status)
+}
+
+pub(crate) fn is_transient_error(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout() || error.is_request()
+}
+
+/// Parse the `Retry-After` header, either given in seconds or as an HTTP date
+pub(crate) fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Instant,
+    };
+
+    use super::*;
+    use crate::test_utils::{MockResponse, MockServer};
+
+    /// Answer `failures` times with `status` then succeed
+    async fn flaky_server(failures: u32, status: u16, retry_after: Option<&str>) -> MockServer {
+        let count = AtomicU32::new(0);
+        let retry_after = retry_after.map(str::to_string);
+        MockServer::start(
+            move |_| match count.fetch_add(1, Ordering::SeqCst) < failures {
+                true => {
+                    let response = MockResponse::new(status);
+                    match &retry_after {
+                        Some(value) => response.header("Retry-After", value),
+                        None => response,
+                    }
+                }
+                false => MockResponse::new(200).body("ok"),
+            },
+        )
+        .await
+    }
+
+    #[test]
+    fn retry_after_is_capped() {
+        let policy = RetryPolicy::default();
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_secs(3600))),
+            policy.max_delay
+        );
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_secs(2))),
+            Duration::from_secs(2)
+        );
+    }
+
+    #[test]
+    fn delay_grows_up_to_max() {
+        let policy = RetryPolicy::default();
+        for attempt in 0..10 {
+            assert!(policy.delay(attempt, None) <= policy.max_delay);
+        }
+        assert!(policy.delay(0, None) <= policy.base_delay);
+    }
+
+    #[tokio::test]
+    async fn retries_gateway_errors() {
+        for status in [502, 503] {
+            let server = flaky_server(2, status, None).await;
+            let client = server.client();
+            let req = client.get(&["about"]).build().unwrap();
+            let resp = client.execute(req).await.unwrap();
+            assert_eq!(resp.text().await.unwrap(), "ok");
+            assert_eq!(server.requests().len(), 3);
+        }
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let server = flaky_server(10, 503, None).await;
+        let client = server.client().with_retry_policy(
+            RetryPolicy::default()
+                .with_max_retries(2)
+                .with_delays(Duration::from_millis(1), Duration::from_millis(1)),
+        );
+        let req = client.get(&["about"]).build().unwrap();
+        let error = client.execute(req).await.unwrap_err();
+        assert_eq!(error.http_status(), Some(StatusCode::SERVICE_UNAVAILABLE));
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_non_idempotent_verbs() {
+        let server = flaky_server(1, 503, None).await;
+        let client = server.client();
+        let req = client.post(&["request"]).body("x").build().unwrap();
+        assert!(client.execute(req).await.is_err());
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/request");
+        assert_eq!(requests[0].body, b"x");
+        assert!(requests[0].headers.iter().any(|(k, _)| k == "host"));
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let server = flaky_server(1, 404, None).await;
+        let client = server.client();
+        let req = client.get(&["about"]).build().unwrap();
+        assert!(client.execute(req).await.is_err());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn honors_retry_after() {
+        let server = flaky_server(1, 429, Some("1")).await;
+        let client = server.client().with_retry_policy(
+            RetryPolicy::default().with_delays(Duration::from_millis(1), Duration::from_secs(5)),
+        );
+        let start = Instant::now();
+        let req = client.get(&["about"]).build().unwrap();
+        client.execute(req).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn caps_retry_after() {
+        let server = flaky_server(1, 503, Some("3600")).await;
+        let client = server.client();
+        let start = Instant::now();
+        let req = client.get(&["about"]).build().unwrap();
+        client.execute(req).await.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+
+use futures_util::StreamExt;
+use tokio::sync::OnceCell;
+use url::Url;
+use zbus::{
+    proxy,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value},
+    Connection, Result,
+};
+
+use crate::{
+    authentication::{askpass, IntoPassword},
+    error::AuthError,
+};
+
+/// Session, parameters, value and content type
+type Secret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+
+#[proxy(
+    interface = "org.freedesktop.Secret.Service",
+    default_service = "org.freedesktop.secrets",
+    default_path = "/org/freedesktop/secrets"
+)]
+trait SecretService {
+    fn open_session(
+        &self,
+        algorithm: &str,
+        input: &Value<'_>,
+    ) -> Result<(OwnedValue, OwnedObjectPath)>;
+    fn unlock(&self, objects: &[ObjectPath<'_>])
+        -> Result<(Vec<OwnedObjectPath>, OwnedObjectPath)>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.Secret.Collection",
+    default_service = "org.freedesktop.secrets"
+)]
+trait Collection {
+    fn search_items(&self, attributes: HashMap<&str, &str>) -> Result<Vec<OwnedObjectPath>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.Secret.Item",
+    default_service = "org.freedesktop.secrets"
+)]
+trait Item {
+    fn get_secret(&self, session: &ObjectPath<'_>) -> Result<Secret>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.Secret.Prompt",
+    default_service = "org.freedesktop.secrets"
+)]
+trait Prompt {
+    fn prompt(&self, window_id: &str) -> Result<()>;
+    #[zbus(signal)]
+    fn completed(&self, dismissed: bool, result: Value<'_>) -> Result<()>;
+}
+
+/// python-keyring stores its passwords in the default collection
+const DEFAULT_COLLECTION: &str = "/org/freedesktop/secrets/aliases/default";
+
+/// Read the password osc stored through python-keyring's `SecretService.Keyring`
+#[derive(Debug, Default)]
+pub struct SecretServiceGetter {
+    service: String,
+    username: String,
+    connection: OnceCell<Connection>,
+}
+
+impl SecretServiceGetter {
+    pub fn new(api_url: &Url, username: &str) -> Self {
+        Self {
+            service: crate::files::keyring_service(api_url),
+            username: username.to_string(),
+            ..Default::default()
+        }
+    }
+
+    async fn get_password(&self) -> Result<Option<String>> {
+        let connection = self.connection.get_or_try_init(Connection::session).await?;
+        let service = SecretServiceProxy::new(connection).await?;
+        let (_, session) = service.open_session("plain", &Value::from("")).await?;
+        let collection = CollectionProxy::builder(connection)
+            .path(DEFAULT_COLLECTION)?
+            .build()
+            .await?;
+        let items = collection
+            .search_items(HashMap::from([
+                ("username", self.username.as_str()),
+                ("service", self.service.as_str()),
+            ]))
+            .await?;
+        let Some(item) = items.first() else {
+            return Ok(None);
+        };
+
+        let (unlocked, prompt) = service.unlock(&[item.as_ref()]).await?;
+        if unlocked.is_empty() && prompt.as_str() != "/" {
+            let prompt = PromptProxy::builder(connection)
+                .path(prompt)?
+                .build()
+                .await?;
+            let mut completed = prompt.receive_completed().await?;
+            prompt.prompt("").await?;
+            if let Some(signal) = completed.next().await {
+                if signal.args()?.dismissed {
+                    return Ok(None);
+                }
+            }
+        }
+
+        let item = ItemProxy::builder(connection)
+            .path(item.as_ref())?
+            .build()
+            .await?;
+        let (_, _, secret, _) = item.get_secret(&session).await?;
+        Ok(String::from_utf8(secret).ok())
+    }
+}
+
+#[async_trait::async_trait]
+impl IntoPassword for SecretServiceGetter {
+    async fn pass(&self) -> std::result::Result<String, AuthError> {
+        match self.get_password().await {
+            Ok(Some(password)) => Ok(password),
+            Ok(None) => {
+                tracing::warn!(
+                    service = self.service,
+                    username = self.username,
+                    "No password found in Secret Service, falling back to Transient"
+                );
+                askpass()
+            }
+            Err(error) => {
+                tracing::warn!(%error, "Unable to query Secret Service, falling back to Transient");
+                askpass()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+        sync::{Arc, Mutex},
+    };
+
+    use zbus::{connection, interface, zvariant::Value};
+
+    use super::*;
+
+    const ITEM: &str = "/org/freedesktop/secrets/collection/login/1";
+
+    /// Private session bus, stopped on drop
+    struct Bus(Child, String);
+
+    impl Bus {
+        fn start() -> Self {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address=1"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .expect("dbus-daemon is required");
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.as_mut().unwrap())
+                .read_line(&mut address)
+                .unwrap();
+            Self(daemon, address.trim().to_string())
+        }
+
+        async fn connect(&self) -> Connection {
+            connection::Builder::address(self.1.as_str())
+                .unwrap()
+                .build()
+                .await
+                .unwrap()
+        }
+    }
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    struct MockService;
+
+    #[interface(name = "org.freedesktop.Secret.Service")]
+    impl MockService {
+        fn open_session(
+            &self,
+            algorithm: &str,
+            _input: Value<'_>,
+        ) -> (OwnedValue, OwnedObjectPath) {
+            assert_eq!(algorithm, "plain");
+            (
+                Value::from("").try_into().unwrap(),
+                OwnedObjectPath::try_from("/org/freedesktop/secrets/session/1").unwrap(),
+            )
+        }
+
+        fn unlock(&self, objects: Vec<OwnedObjectPath>) -> (Vec<OwnedObjectPath>, OwnedObjectPath) {
+            (objects, OwnedObjectPath::try_from("/").unwrap())
+        }
+    }
+
+    /// Default collection holding a single item, recording the searched attributes
+    struct MockCollection {
+        attributes: HashMap<String, String>,
+        searches: Arc<Mutex<Vec<HashMap<String, String>>>>,
+    }
+
+    #[interface(name = "org.freedesktop.Secret.Collection")]
+    impl MockCollection {
+        fn search_items(&self, attributes: HashMap<String, String>) -> Vec<OwnedObjectPath> {
+            let found = attributes == self.attributes;
+            self.searches.lock().unwrap().push(attributes);
+            match found {
+                true => vec![OwnedObjectPath::try_from(ITEM).unwrap()],
+                false => Vec::new(),
+            }
+        }
+    }
+
+    struct MockItem;
+
+    #[interface(name = "org.freedesktop.Secret.Item")]
+    impl MockItem {
+        fn get_secret(&self, session: OwnedObjectPath) -> Secret {
+            (
+                session,
+                Vec::new(),
+                b"secret".to_vec(),
+                "text/plain".to_string(),
+            )
+        }
+    }
+
+    /// Serve a keyring holding the password python-keyring stores for osc
+    async fn serve(bus: &Bus, searches: Arc<Mutex<Vec<HashMap<String, String>>>>) -> Connection {
+        let attributes = HashMap::from([
+            ("service".to_string(), "api.example.org".to_string()),
+            ("username".to_string(), "user".to_string()),
+        ]);
+        connection::Builder::address(bus.1.as_str())
+            .unwrap()
+            .name("org.freedesktop.secrets")
+            .unwrap()
+            .serve_at("/org/freedesktop/secrets", MockService)
+            .unwrap()
+            .serve_at(
+                DEFAULT_COLLECTION,
+                MockCollection {
+                    attributes,
+                    searches,
+                },
+            )
+            .unwrap()
+            .serve_at(ITEM, MockItem)
+            .unwrap()
+            .build()
+            .await
+            .unwrap()
+    }
+
+    fn getter(api_url: &str, username: &str) -> SecretServiceGetter {
+        SecretServiceGetter::new(&Url::parse(api_url).unwrap(), username)
+    }
+
+    #[tokio::test]
+    async fn reads_python_keyring_password() {
+        let bus = Bus::start();
+        let searches = Arc::new(Mutex::new(Vec::new()));
+        let _service = serve(&bus, searches.clone()).await;
+
+        let getter = getter("https://api.example.org", "user");
+        getter.connection.set(bus.connect().await).unwrap();
+        assert_eq!(
+            getter.get_password().await.unwrap().as_deref(),
+            Some("secret")
+        );
+        assert_eq!(
+            searches.lock().unwrap()[0],
+            HashMap::from([
+                ("service".to_string(), "api.example.org".to_string()),
+                ("username".to_string(), "user".to_string()),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_item_is_none() {
+        let bus = Bus::start();
+        let searches = Arc::new(Mutex::new(Vec::new()));
+        let _service = serve(&bus, searches.clone()).await;
+
+        let getter = getter("https://api.example.org:8443", "user");
+        getter.connection.set(bus.connect().await).unwrap();
+        assert_eq!(getter.get_password().await.unwrap(), None);
+        assert_eq!(
+            searches.lock().unwrap()[0]["service"],
+            "api.example.org:8443"
+        );
+    }
+}
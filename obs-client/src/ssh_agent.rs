@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use ssh_encoding::{Decode, Encode};
+use ssh_key::{Algorithm, PublicKey, Signature};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::UnixStream,
+};
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+const SSH_AGENT_RSA_SHA2_512: u32 = 4;
+
+/// Minimal client of the ssh-agent protocol, enough to list keys and sign with them
+#[derive(Debug, Clone)]
+pub(crate) struct SshAgent {
+    socket: PathBuf,
+}
+
+impl SshAgent {
+    /// The agent pointed to by `SSH_AUTH_SOCK`, if any
+    pub(crate) fn from_env() -> Option<Self> {
+        Some(Self::new(std::env::var_os("SSH_AUTH_SOCK")?.into()))
+    }
+
+    pub(crate) fn new(socket: PathBuf) -> Self {
+        Self { socket }
+    }
+
+    pub(crate) async fn identities(&self) -> ssh_key::Result<Vec<PublicKey>> {
+        let response = self.request(&[SSH_AGENTC_REQUEST_IDENTITIES]).await?;
+        let reader = &mut expect_message(&response, SSH_AGENT_IDENTITIES_ANSWER)?;
+        let count = u32::decode(reader)?;
+        let mut keys = Vec::new();
+        for _ in 0..count {
+            let key_blob = Vec::<u8>::decode(reader)?;
+            let comment = String::decode(reader)?;
+            // Skip key types we can't parse (e.g. certificates) instead of failing
+            if let Ok(mut key) = PublicKey::from_bytes(&key_blob) {
+                key.set_comment(comment);
+                keys.push(key);
+            }
+        }
+        Ok(keys)
+    }
+
+    pub(crate) async fn sign(&self, key: &PublicKey, data: &[u8]) -> ssh_key::Result<Signature> {
+        let flags = match key.algorithm() {
+            Algorithm::Rsa { .. } => SSH_AGENT_RSA_SHA2_512,
+            _ => 0,
+        };
+        let mut message = vec![SSH_AGENTC_SIGN_REQUEST];
+        key.to_bytes()?.encode(&mut message)?;
+        data.encode(&mut message)?;
+        flags.encode(&mut message)?;
+        let response = self.request(&message).await?;
+        let reader = &mut expect_message(&response, SSH_AGENT_SIGN_RESPONSE)?;
+        let signature = Vec::<u8>::decode(reader)?;
+        Signature::try_from(signature.as_slice())
+    }
+
+    async fn request(&self, message: &[u8]) -> ssh_key::Result<Vec<u8>> {
+        let mut stream = UnixStream::connect(&self.socket).await?;
+        let mut frame = Vec::with_capacity(message.len() + 4);
+        message.encode(&mut frame)?;
+        stream.write_all(&frame).await?;
+        let len = stream.read_u32().await?;
+        let mut response = vec![0u8; len as usize];
+        stream.read_exact(&mut response).await?;
+        Ok(response)
+    }
+}
+
+fn expect_message(response: &[u8], expected: u8) -> ssh_key::Result<&[u8]> {
+    match response.split_first() {
+        Some((kind, body)) if *kind == expected => Ok(body),
+        Some((&SSH_AGENT_FAILURE, _)) => Err(ssh_key::Error::Crypto),
+        _ => Err(ssh_key::Error::FormatEncoding),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{fixture_key, MockAgent};
+
+    #[tokio::test]
+    async fn lists_identities() {
+        let mock = MockAgent::start(vec![
+            fixture_key("id_ed25519"),
+            fixture_key("id_ed25519_encrypted"),
+        ]);
+        let keys = mock.agent().identities().await.unwrap();
+        let comments: Vec<&str> = keys.iter().map(|k| k.comment()).collect();
+        assert_eq!(comments, ["user@example.com", "encrypted@example.com"]);
+        assert_eq!(
+            keys[0].key_data(),
+            fixture_key("id_ed25519").public_key().key_data()
+        );
+    }
+
+    #[tokio::test]
+    async fn signs_with_held_key_only() {
+        let mock = MockAgent::start(vec![fixture_key("id_ed25519")]);
+        let agent = mock.agent();
+        let key = fixture_key("id_ed25519");
+        let signature = agent.sign(key.public_key(), b"data").await.unwrap();
+        assert_eq!(signature.algorithm(), Algorithm::Ed25519);
+
+        let other = fixture_key("id_ed25519_encrypted");
+        assert!(matches!(
+            agent.sign(other.public_key(), b"data").await,
+            Err(ssh_key::Error::Crypto)
+        ));
+    }
+
+    #[tokio::test]
+    async fn missing_socket_is_an_error() {
+        let agent = SshAgent::new("/nonexistent/agent.sock".into());
+        assert!(matches!(
+            agent.identities().await,
+            Err(ssh_key::Error::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,430 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use openssl::{
+    asn1::Asn1Time,
+    bn::BigNum,
+    hash::MessageDigest,
+    pkey::{PKey, Private},
+    rsa::Rsa,
+    x509::{
+        extension::{BasicConstraints, SubjectAlternativeName},
+        X509NameBuilder, X509,
+    },
+};
+use signature::Signer;
+use ssh_encoding::{Decode, Encode};
+use ssh_key::{PrivateKey, PublicKey};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, UnixListener, UnixStream},
+};
+use tokio_native_tls::{native_tls, TlsAcceptor};
+use url::Url;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::{
+    authentication::BasicAuth, client::OBSClient, retry::RetryPolicy, ssh_agent::SshAgent,
+};
+
+/// Request received by the [`MockServer`]
+#[derive(Debug, Clone)]
+pub(crate) struct MockRequest {
+    pub method: String,
+    /// Path and query, e.g. `/source/prj?cmd=commitfilelist`
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct MockResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    chunks: Vec<Vec<u8>>,
+}
+
+impl MockResponse {
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            chunks: Vec::new(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.chunks = vec![body.into()];
+        self
+    }
+
+    /// Stream the body with `Transfer-Encoding: chunked`, one HTTP chunk per item
+    pub fn chunks(mut self, chunks: &[&str]) -> Self {
+        self.chunks = chunks.iter().map(|c| c.as_bytes().to_vec()).collect();
+        self.header("Transfer-Encoding", "chunked")
+    }
+
+    fn is_chunked(&self) -> bool {
+        self.headers
+            .iter()
+            .any(|(k, _)| k.eq_ignore_ascii_case("transfer-encoding"))
+    }
+}
+
+type Handler = dyn Fn(&MockRequest) -> MockResponse + Send + Sync;
+
+/// Minimal HTTP/1.1 server answering every request with `handler`, closing the
+/// connection after each response
+pub(crate) struct MockServer {
+    url: Url,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockServer {
+    pub async fn start(
+        handler: impl Fn(&MockRequest) -> MockResponse + Send + Sync + 'static,
+    ) -> Self {
+        Self::serve(None, Arc::new(handler)).await
+    }
+
+    /// Serve over TLS with the given certificate, reachable as `https://localhost`
+    pub async fn start_tls(
+        identity: native_tls::Identity,
+        handler: impl Fn(&MockRequest) -> MockResponse + Send + Sync + 'static,
+    ) -> Self {
+        let acceptor = native_tls::TlsAcceptor::new(identity).unwrap();
+        Self::serve(Some(acceptor.into()), Arc::new(handler)).await
+    }
+
+    async fn serve(tls: Option<TlsAcceptor>, handler: Arc<Handler>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let url = match tls {
+            Some(_) => format!("https://localhost:{}/", port),
+            None => format!("http://127.0.0.1:{}/", port),
+        };
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        let tls = tls.map(Arc::new);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                let received = received.clone();
+                let tls = tls.clone();
+                tokio::spawn(async move {
+                    match tls {
+                        Some(tls) => {
+                            if let Ok(stream) = tls.accept(stream).await {
+                                handle(stream, handler.as_ref(), &received).await;
+                            }
+                        }
+                        None => handle(stream, handler.as_ref(), &received).await,
+                    }
+                });
+            }
+        });
+        Self {
+            url: Url::parse(&url).unwrap(),
+            requests,
+        }
+    }
+
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Client authenticating as `user`/`password`, retrying with very short delays
+    pub fn client(&self) -> OBSClient {
+        OBSClient::builder()
+            .api_url(self.url())
+            .authenticator(Arc::new(BasicAuth {
+                username: "user".to_string(),
+                password: Box::new("password".to_string()),
+            }))
+            .retry_policy(
+                RetryPolicy::default()
+                    .with_delays(Duration::from_millis(1), Duration::from_millis(10)),
+            )
+            .build()
+            .unwrap()
+    }
+}
+
+async fn handle<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    handler: &Handler,
+    received: &Mutex<Vec<MockRequest>>,
+) {
+    if let Some(request) = read_request(&mut stream).await {
+        let response = handler(&request);
+        received.lock().unwrap().push(request);
+        write_response(&mut stream, response).await;
+    }
+}
+
+async fn read_request<S: AsyncRead + Unpin>(stream: &mut S) -> Option<MockRequest> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(i) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break i;
+        }
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+        }
+    };
+    let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect();
+    let length = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.parse::<usize>().ok())
+        .unwrap_or_default();
+    while buffer.len() < head_end + 4 + length {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+        }
+    }
+    Some(MockRequest {
+        method,
+        path,
+        headers,
+        body: buffer[head_end + 4..head_end + 4 + length].to_vec(),
+    })
+}
+
+async fn write_response<S: AsyncWrite + Unpin>(stream: &mut S, response: MockResponse) {
+    let mut head = format!("HTTP/1.1 {} Mock\r\nConnection: close\r\n", response.status);
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    let chunked = response.is_chunked();
+    if !chunked {
+        let length: usize = response.chunks.iter().map(Vec::len).sum();
+        head.push_str(&format!("Content-Length: {}\r\n", length));
+    }
+    head.push_str("\r\n");
+    if stream.write_all(head.as_bytes()).await.is_err() {
+        return;
+    }
+    for chunk in &response.chunks {
+        let result = match chunked {
+            true => {
+                let mut framed = format!("{:x}\r\n", chunk.len()).into_bytes();
+                framed.extend_from_slice(chunk);
+                framed.extend_from_slice(b"\r\n");
+                stream.write_all(&framed).await
+            }
+            false => stream.write_all(chunk).await,
+        };
+        if result.is_err() || stream.flush().await.is_err() {
+            return;
+        }
+        if chunked {
+            // Let the client see the chunks separately
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+    if chunked {
+        let _ = stream.write_all(b"0\r\n\r\n").await;
+    }
+    let _ = stream.shutdown().await;
+}
+
+/// Self signed certificate authority, returned with its key
+pub(crate) fn certificate_authority() -> (X509, PKey<Private>) {
+    let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    let mut builder = certificate_builder("Test CA", &key);
+    builder
+        .append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+        .unwrap();
+    builder.sign(&key, MessageDigest::sha256()).unwrap();
+    (builder.build(), key)
+}
+
+/// Identity for `localhost`, signed by the certificate authority
+pub(crate) fn server_identity(ca: &X509, ca_key: &PKey<Private>) -> native_tls::Identity {
+    let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    let mut builder = certificate_builder("localhost", &key);
+    builder.set_issuer_name(ca.subject_name()).unwrap();
+    let san = SubjectAlternativeName::new()
+        .dns("localhost")
+        .build(&builder.x509v3_context(Some(ca), None))
+        .unwrap();
+    builder.append_extension(san).unwrap();
+    builder.sign(ca_key, MessageDigest::sha256()).unwrap();
+    native_tls::Identity::from_pkcs8(
+        &builder.build().to_pem().unwrap(),
+        &key.private_key_to_pem_pkcs8().unwrap(),
+    )
+    .unwrap()
+}
+
+fn certificate_builder(common_name: &str, key: &PKey<Private>) -> openssl::x509::X509Builder {
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_text("CN", common_name).unwrap();
+    let name = name.build();
+    let mut builder = X509::builder().unwrap();
+    builder.set_version(2).unwrap();
+    builder
+        .set_serial_number(
+            &BigNum::from_u32(fastrand::u32(..))
+                .unwrap()
+                .to_asn1_integer()
+                .unwrap(),
+        )
+        .unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder.set_issuer_name(&name).unwrap();
+    builder.set_pubkey(key).unwrap();
+    builder
+        .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    builder
+        .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    builder
+}
+
+/// Response body from `tests/fixtures/api`
+pub(crate) fn api_fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/api")
+        .join(name);
+    std::fs::read_to_string(path).unwrap()
+}
+
+/// Key pair from `tests/fixtures/ssh`, decrypted with `passphrase` if needed
+pub(crate) fn fixture_key(name: &str) -> PrivateKey {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/ssh")
+        .join(name);
+    let key = PrivateKey::read_openssh_file(&path).unwrap();
+    match key.is_encrypted() {
+        true => key.decrypt("passphrase").unwrap(),
+        false => key,
+    }
+}
+
+/// Stand-in ssh-agent holding `keys`, listening in a temporary directory
+pub(crate) struct MockAgent {
+    socket: tempfile::TempDir,
+}
+
+impl MockAgent {
+    pub fn start(keys: Vec<PrivateKey>) -> Self {
+        let socket = tempfile::tempdir().unwrap();
+        let listener = UnixListener::bind(socket.path().join("agent.sock")).unwrap();
+        let keys = Arc::new(keys);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_agent(stream, keys.clone()));
+            }
+        });
+        Self { socket }
+    }
+
+    pub fn socket(&self) -> std::path::PathBuf {
+        self.socket.path().join("agent.sock")
+    }
+
+    pub fn agent(&self) -> SshAgent {
+        SshAgent::new(self.socket())
+    }
+}
+
+async fn serve_agent(mut stream: UnixStream, keys: Arc<Vec<PrivateKey>>) {
+    while let Ok(len) = stream.read_u32().await {
+        let mut message = vec![0u8; len as usize];
+        if stream.read_exact(&mut message).await.is_err() {
+            return;
+        }
+        let response = agent_response(&message, &keys).unwrap_or_else(|| vec![5]);
+        let mut frame = Vec::new();
+        response.encode(&mut frame).unwrap();
+        if stream.write_all(&frame).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn agent_response(message: &[u8], keys: &[PrivateKey]) -> Option<Vec<u8>> {
+    let (kind, reader) = message.split_first()?;
+    let reader = &mut &reader[..];
+    let mut response = Vec::new();
+    match kind {
+        // SSH_AGENTC_REQUEST_IDENTITIES
+        11 => {
+            response.push(12);
+            (keys.len() as u32).encode(&mut response).ok()?;
+            for key in keys {
+                key.public_key()
+                    .to_bytes()
+                    .ok()?
+                    .encode(&mut response)
+                    .ok()?;
+                key.comment().encode(&mut response).ok()?;
+            }
+        }
+        // SSH_AGENTC_SIGN_REQUEST
+        13 => {
+            let public_key = PublicKey::from_bytes(&Vec::<u8>::decode(reader).ok()?).ok()?;
+            let data = Vec::<u8>::decode(reader).ok()?;
+            let key = keys
+                .iter()
+                .find(|k| k.public_key().key_data() == public_key.key_data())?;
+            let signature: ssh_key::Signature = key.try_sign(&data).ok()?;
+            let mut blob = Vec::new();
+            signature.encode(&mut blob).ok()?;
+            response.push(14);
+            blob.encode(&mut response).ok()?;
+        }
+        _ => return None,
+    }
+    Some(response)
+}
+
+/// Elements, sorted attributes and non blank text of an XML document, in document order
+pub(crate) fn xml_events(input: &str) -> Vec<String> {
+    EventReader::from_str(input)
+        .into_iter()
+        .filter_map(|event| match event.unwrap() {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let mut attributes: Vec<String> = attributes
+                    .iter()
+                    .map(|a| format!(" {}={:?}", a.name.local_name, a.value))
+                    .collect();
+                attributes.sort();
+                Some(format!("<{}{}>", name.local_name, attributes.concat()))
+            }
+            XmlEvent::EndElement { name } => Some(format!("</{}>", name.local_name)),
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                Some(text.trim().to_string()).filter(|t| !t.is_empty())
+            }
+            _ => None,
+        })
+        .collect()
+}
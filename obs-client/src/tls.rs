@@ -0,0 +1,184 @@
+use std::path::Path;
+
+use reqwest::Certificate;
+
+use crate::error::ClientError;
+
+/// Load all certificates from a file, either a PEM bundle or a single DER certificate
+pub(crate) fn load_certificates_file(path: &Path) -> Result<Vec<Certificate>, ClientError> {
+    let error =
+        |e: &dyn std::fmt::Display| ClientError::CertificateError(path.into(), e.to_string());
+    let content = std::fs::read(path).map_err(|e| error(&e))?;
+    let certificates = Certificate::from_pem_bundle(&content).map_err(|e| error(&e))?;
+    if !certificates.is_empty() {
+        return Ok(certificates);
+    }
+    Ok(vec![Certificate::from_der(&content).map_err(|e| error(&e))?])
+}
+
+/// Load certificates from an OpenSSL style hashed directory (see `openssl rehash`).
+///
+/// Only `<hash>.<n>` entries are loaded, so certificates present both as a file and as
+/// a hash symlink are not added twice. Directories which were never rehashed fall back
+/// to every `*.pem` and `*.crt` file.
+pub(crate) fn load_certificates_dir(path: &Path) -> Result<Vec<Certificate>, ClientError> {
+    let mut entries: Vec<_> = std::fs::read_dir(path)
+        .map_err(|e| ClientError::CertificateError(path.into(), e.to_string()))?
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+    entries.sort();
+    let hashed: Vec<_> = entries
+        .iter()
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(is_hashed_name)
+        })
+        .collect();
+    let selected = match hashed.is_empty() {
+        false => hashed,
+        true => entries
+            .iter()
+            .filter(|p| {
+                p.extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| e == "pem" || e == "crt")
+            })
+            .collect(),
+    };
+    let mut certificates = Vec::new();
+    for entry in selected {
+        certificates.extend(load_certificates_file(entry)?);
+    }
+    Ok(certificates)
+}
+
+fn is_hashed_name(name: &str) -> bool {
+    match name.split_once('.') {
+        Some((hash, index)) => {
+            hash.len() == 8
+                && hash.chars().all(|c| c.is_ascii_hexdigit())
+                && !index.is_empty()
+                && index.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    use super::*;
+    use crate::{
+        authentication::BasicAuth,
+        client::OBSClient,
+        test_utils::{certificate_authority, server_identity, MockResponse, MockServer},
+    };
+
+    fn client(server: &MockServer) -> crate::client::OBSClientBuilder {
+        OBSClient::builder()
+            .api_url(server.url())
+            .authenticator(Arc::new(BasicAuth {
+                username: "user".to_string(),
+                password: Box::new("password".to_string()),
+            }))
+            .retry_policy(crate::retry::RetryPolicy::none())
+    }
+
+    async fn get(client: &OBSClient) -> Result<String, crate::error::APIError> {
+        let req = client.get(&["about"]).build()?;
+        Ok(client.execute(req).await?.text().await?)
+    }
+
+    #[tokio::test]
+    async fn trusts_ca_file() {
+        let (ca, ca_key) = certificate_authority();
+        let server = MockServer::start_tls(server_identity(&ca, &ca_key), |_| {
+            MockResponse::new(200).body("ok")
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let cafile = dir.path().join("ca.pem");
+        std::fs::write(&cafile, ca.to_pem().unwrap()).unwrap();
+
+        let untrusted = client(&server).build().unwrap();
+        assert!(get(&untrusted).await.is_err());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Test", HeaderValue::from_static("value"));
+        let trusted = client(&server)
+            .ca_file(&cafile)
+            .http_headers(headers)
+            .build()
+            .unwrap();
+        assert_eq!(get(&trusted).await.unwrap(), "ok");
+        assert!(server.requests().iter().any(|r| r
+            .headers
+            .contains(&("x-test".to_string(), "value".to_string()))));
+    }
+
+    #[tokio::test]
+    async fn trusts_der_ca_file() {
+        let (ca, ca_key) = certificate_authority();
+        let server = MockServer::start_tls(server_identity(&ca, &ca_key), |_| {
+            MockResponse::new(200).body("ok")
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let cafile = dir.path().join("ca.der");
+        std::fs::write(&cafile, ca.to_der().unwrap()).unwrap();
+        let trusted = client(&server).ca_file(&cafile).build().unwrap();
+        assert_eq!(get(&trusted).await.unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn trusts_hashed_ca_path() {
+        let (ca, ca_key) = certificate_authority();
+        let server = MockServer::start_tls(server_identity(&ca, &ca_key), |_| {
+            MockResponse::new(200).body("ok")
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let name = format!("{:08x}.0", ca.subject_name_hash());
+        std::fs::write(dir.path().join(name), ca.to_pem().unwrap()).unwrap();
+        let trusted = client(&server).ca_path(dir.path()).build().unwrap();
+        assert_eq!(get(&trusted).await.unwrap(), "ok");
+    }
+
+    #[test]
+    fn hashed_directory_skips_duplicates() {
+        let (ca, _) = certificate_authority();
+        let dir = tempfile::tempdir().unwrap();
+        let pem = ca.to_pem().unwrap();
+        std::fs::write(dir.path().join("ca.pem"), &pem).unwrap();
+        std::fs::write(dir.path().join("0123abcd.0"), &pem).unwrap();
+        assert_eq!(load_certificates_dir(dir.path()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn unhashed_directory_loads_pem_files() {
+        let (ca, _) = certificate_authority();
+        let (other, _) = certificate_authority();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.pem"), ca.to_pem().unwrap()).unwrap();
+        std::fs::write(dir.path().join("b.crt"), other.to_pem().unwrap()).unwrap();
+        std::fs::write(dir.path().join("README"), "not a certificate").unwrap();
+        assert_eq!(load_certificates_dir(dir.path()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn invalid_file_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ca.pem");
+        std::fs::write(&path, "garbage").unwrap();
+        assert!(matches!(
+            load_certificates_file(&path),
+            Err(ClientError::CertificateError(p, _)) if p == path
+        ));
+    }
+}
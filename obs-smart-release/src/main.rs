@@ -15,18 +15,21 @@ use obs_client::{
         project::{Binary, BinaryList, Project, ProjectKind, ReleaseTrigger, Repository},
         BuildArch,
     },
+    client::{OBSClient, OscrcOverrides},
     files::{ContainerInfo, HelmInfo, Oscrc},
 };
-use url::Url;
 
 #[derive(Parser, Debug)]
 struct Cli {
     #[arg(short, long)]
-    api_url: Option<Url>,
+    api_url: Option<String>,
     #[arg(short, long)]
     username: Option<String>,
     #[arg(short, long)]
     password: Option<String>,
+    /// Keep the password asked for in the kernel keyring, when oscrc uses it
+    #[arg(long)]
+    store_password: bool,
     project: String,
 
     #[arg(short, long, action=clap::ArgAction::Count)]
@@ -65,38 +68,20 @@ async fn main() {
     VERBOSITY.fetch_add(args.verbose, Ordering::AcqRel);
     VERBOSITY.fetch_sub(args.quiet, Ordering::AcqRel);
     let cfg = Oscrc::new(None).unwrap();
-    let jar = obs_client::get_osc_cookiejar(&cfg).unwrap();
-    let api_url = args.api_url.unwrap_or(cfg.apiurl.clone());
-    let username = args
-        .username
-        .unwrap_or(cfg.hosts_options[&api_url].username.clone());
-
     let theme = ColorfulTheme::default();
 
-    let auhtenticator: Arc<dyn obs_client::authentication::AuthMethod> = match &cfg.hosts_options
-        [&api_url]
-        .sshkey
-    {
-        Some(key) => Arc::new(obs_client::authentication::SSHAuth::new(&username, key).unwrap()),
-        None => {
-            let auth: Arc<dyn obs_client::authentication::AuthMethod> = match &cfg.sshkey {
-                Some(key) => {
-                    Arc::new(obs_client::authentication::SSHAuth::new(&username, key).unwrap())
-                }
-                None => Arc::new(obs_client::authentication::BasicAuth {
-                    username,
-                    password: match args.password {
-                        Some(pass) => Box::new(pass),
-                        None => cfg.get_password_provider(&api_url),
-                    },
-                }),
-            };
-            auth
-        }
-    };
-
     let client = Arc::new(
-        obs_client::client::OBSClient::new(api_url.clone(), auhtenticator, Some(jar)).unwrap(),
+        OBSClient::from_oscrc(
+            &cfg,
+            args.api_url.as_deref(),
+            OscrcOverrides {
+                username: args.username,
+                password: args.password,
+                store_password: args.store_password,
+                ..Default::default()
+            },
+        )
+        .unwrap(),
     );
 
     let project = obs_client::api::project::Project::from_name(client.clone(), &args.project);
@@ -107,7 +92,7 @@ async fn main() {
         .flat_map(|r| {
             r.releasetarget
                 .iter()
-                .filter(|t| t.trigger == ReleaseTrigger::Manual)
+                .filter(|t| t.trigger == Some(ReleaseTrigger::Manual))
                 .map(|t| ReleaseTarget {
                     src_repository: Repository::from_name_project(&r.name, &project),
                     dest_repository: Repository::from_name_project(
@@ -162,12 +147,24 @@ async fn main() {
 
     let src_binlist = project.binarylist().await.unwrap();
     let mut packages_to_release: Vec<(Package, Repository, Repository)> = Default::default();
+    #[allow(clippy::mutable_key_type)]
+    let mut build_archs: HashMap<(Package, Repository), BuildArch> = Default::default();
 
     for (package, bins) in src_binlist.binaries {
         let mut release_for_repo: Vec<(Repository, &Repository)> = Default::default();
         for (repo, bins) in bins {
-            if bins.iter().all(|(_, b)| b.is_empty()) {
-                continue;
+            // Show the build of the first architecture with binaries, by name so the
+            // choice doesn't depend on the map order
+            let built_arch = bins
+                .iter()
+                .filter(|(_, b)| !b.is_empty())
+                .map(|(arch, _)| arch)
+                .min_by_key(|arch| arch.to_string());
+            match built_arch {
+                Some(arch) => {
+                    build_archs.insert((package.clone(), repo.clone()), arch.clone());
+                }
+                None => continue,
             }
             for target in release_targets.iter().filter(|t| t.src_repository == repo) {
                 if full_release_projects.contains(target.dest_repository.project()) {
@@ -244,13 +241,34 @@ async fn main() {
                 )
             });
     for (package, src_rep, dst_rep) in packages_to_release.iter() {
+        // Show which build is about to be released
+        let last_build = match build_archs.get(&(package.clone(), src_rep.clone())) {
+            Some(arch) => match package.build_history(src_rep.name(), arch.clone()).await {
+                // Oldest first
+                Ok(mut history) => history.pop(),
+                Err(e) => {
+                    warn!(
+                        "Could not get the build history of {} in {}: {}",
+                        package.name(),
+                        src_rep.name(),
+                        e
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
         select = select.item_checked(
             format!(
-                "{:<max_package$}\t{:>max_src$} -> {}/{}",
+                "{:<max_package$}\t{:>max_src$} -> {}/{}\t{}",
                 package.name(),
                 src_rep.name(),
                 dst_rep.project().name(),
-                dst_rep.name()
+                dst_rep.name(),
+                last_build.map_or("unknown build".to_string(), |b| format!(
+                    "{} (srcmd5 {})",
+                    b.versrel, b.srcmd5
+                )),
             ),
             true,
         );
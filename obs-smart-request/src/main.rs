@@ -1,26 +1,31 @@
 use std::sync::Arc;
 
 use clap::Parser;
-use itertools::Itertools;
 use obs_client::{
-    api::{project::Project, request::Request},
+    api::{
+        project::Project,
+        request::{Request, RequestQuery, RequestRole, RequestState},
+    },
+    client::{OBSClient, OscrcOverrides},
     files::Oscrc,
 };
 use package_solver::get_actions;
 use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
-use url::Url;
 
 mod package_solver;
 
 #[derive(Parser, Debug)]
 struct Cli {
     #[arg(short, long)]
-    api_url: Option<Url>,
+    api_url: Option<String>,
     #[arg(short, long)]
     username: Option<String>,
     #[arg(short, long)]
     password: Option<String>,
+    /// Keep the password asked for in the kernel keyring, when oscrc uses it
+    #[arg(long)]
+    store_password: bool,
     #[arg(long)]
     dry_run: bool,
     src_project: String,
@@ -38,36 +43,18 @@ async fn main() {
     let args = Cli::parse();
 
     let cfg = Oscrc::new(None).unwrap();
-    let jar = obs_client::get_osc_cookiejar(&cfg).unwrap();
-    let api_url = args.api_url.unwrap_or(cfg.apiurl.clone());
-    let username = args
-        .username
-        .unwrap_or(cfg.hosts_options[&api_url].username.clone());
-
-    let auhtenticator: Arc<dyn obs_client::authentication::AuthMethod> = match &cfg.hosts_options
-        [&api_url]
-        .sshkey
-    {
-        Some(key) => Arc::new(obs_client::authentication::SSHAuth::new(&username, key).unwrap()),
-        None => {
-            let auth: Arc<dyn obs_client::authentication::AuthMethod> = match &cfg.sshkey {
-                Some(key) => {
-                    Arc::new(obs_client::authentication::SSHAuth::new(&username, key).unwrap())
-                }
-                None => Arc::new(obs_client::authentication::BasicAuth {
-                    username,
-                    password: match args.password {
-                        Some(pass) => Box::new(pass),
-                        None => cfg.get_password_provider(&api_url),
-                    },
-                }),
-            };
-            auth
-        }
-    };
-
     let client = Arc::new(
-        obs_client::client::OBSClient::new(api_url.clone(), auhtenticator, Some(jar)).unwrap(),
+        OBSClient::from_oscrc(
+            &cfg,
+            args.api_url.as_deref(),
+            OscrcOverrides {
+                username: args.username,
+                password: args.password,
+                store_password: args.store_password,
+                ..Default::default()
+            },
+        )
+        .unwrap(),
     );
     let src_project = Project::from_name(client.clone(), &args.src_project);
     let dst_project = Project::from_name(client.clone(), &args.dst_project);
@@ -80,12 +67,12 @@ async fn main() {
         return;
     }
 
-    let requests = Request::mine(client.clone())
-        .await
-        .unwrap()
-        .into_iter()
-        .filter(|r| r.is_for_project(&dst_project))
-        .collect_vec();
+    let pending = RequestQuery::default()
+        .project(&dst_project.name())
+        .role(RequestRole::Target)
+        .state(RequestState::New)
+        .state(RequestState::Review);
+    let requests = Request::search(client.clone(), &pending).await.unwrap();
     if !requests.is_empty() {
         warn!("Existing pending request for destination project, doing nothing");
         return;
@@ -52,11 +52,12 @@ pub async fn get_actions(
             info!(src_package=%package.package, dst_project=destination_project.name(), "Add this package to the request");
             actions.insert(Action::Submit {
                 source: package.package.clone(),
-                source_rev: Some(package.rev),
+                source_rev: Some(package.rev.to_string()),
                 target: Package::from_name(
                     package.package.name().to_string(),
                     destination_project.clone(),
                 ),
+                options: Default::default(),
             });
         }
 
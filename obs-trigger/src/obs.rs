@@ -4,7 +4,10 @@ use reqwest::Url;
 use serde::Deserialize;
 use tracing::info;
 
-use obs_client::{authentication::BasicAuth, client::OBSClient};
+use obs_client::{
+    authentication::{BasicAuth, TokenAuth},
+    client::OBSClient,
+};
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(untagged)]
@@ -23,8 +26,16 @@ pub async fn new_client(uri: Url, creds: ObsCredentials) -> anyhow::Result<Arc<O
             let login_path: PathBuf = [&path, "username"].iter().collect();
             let mut login = String::default();
             std::fs::File::open(&login_path)?.read_to_string(&mut login)?;
+            let token_path: PathBuf = [&path, "token"].iter().collect();
             let sshkey_path: PathBuf = [&path, "ssh-privatekey"].iter().collect();
-            if sshkey_path.is_file() {
+            if token_path.is_file() {
+                let mut token = String::default();
+                std::fs::File::open(&token_path)?.read_to_string(&mut token)?;
+                Arc::new(TokenAuth {
+                    username: login,
+                    token: token.trim().to_string(),
+                })
+            } else if sshkey_path.is_file() {
                 Arc::new(obs_client::authentication::SSHAuth::new(
                     &login,
                     sshkey_path.to_str().unwrap(),
@@ -41,7 +52,7 @@ pub async fn new_client(uri: Url, creds: ObsCredentials) -> anyhow::Result<Arc<O
         }
     };
     let client = OBSClient::new(uri, authenticator, None)?;
-    let obs_url = client.get_obs_route(&[]).await;
+    let obs_url = client.get_obs_route(&[]).await?;
     info!(%obs_url, "Connected to OBS");
     Ok(Arc::new(client))
 }
@@ -55,6 +55,7 @@ async fn get_projects(State(state): State<WebAppState>) -> Json<GetProjectsRespo
 struct GetProjectResponse {
     name: String,
     ready: bool,
+    failed_packages: Vec<String>,
     monitor_url: Url,
 }
 
@@ -66,19 +67,29 @@ async fn get_project(
         return Err(StatusCode::NOT_FOUND);
     }
     let project = obs_client::api::project::Project::from_name(state.obs_client.clone(), &project);
-    let summary = match project.summary().await {
+    let summary = match project.build_results(&Default::default()).await {
         Ok(p) => p,
         Err(_) => {
             return Err(StatusCode::INTERNAL_SERVER_ERROR);
         }
     };
-    let monitor_url = state
+    let monitor_url = match state
         .obs_client
         .get_obs_route(&["project", "monitor", &project.name()])
-        .await;
+        .await
+    {
+        Ok(u) => u,
+        Err(_) => {
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
     Ok(Json(GetProjectResponse {
         name: project.name(),
         ready: summary.is_all_packages_ok() && summary.is_all_published(),
+        failed_packages: summary
+            .failed_packages()
+            .map(|(repo, arch, p)| format!("{}/{}/{}", repo, arch, p.package))
+            .collect(),
         monitor_url,
     }))
 }